@@ -2,11 +2,23 @@ use anchor_lang::prelude::*;
 use anchor_lang::solana_program::program_option::COption;
 use anchor_spl::token::{self, Mint, TokenAccount, Token};
 use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
 
 declare_id!("8SxMuTujbukR6KbqJXUuVAdTg6kBWtpmCTb6xpYWqwrP");
 pub const FCTR: u64 = 1_000_000_000_000;
 pub const BCDEV: u64 = 1_000_000_000_000_000_000;
 pub const ACCURACY: u64 = 100_000;
+/// Basis points in 100%
+pub const BPS: u64 = 10_000;
+/// Fixed point precision for bonding curve calculations
+pub const CURVE_PRECISION: u128 = 1_000_000_000_000;
+/// Natural logarithm of 2 multiplied by CURVE_PRECISION
+pub const CURVE_LN2: u128 = 693_147_180_560;
+/// Bounds of time after stuck round end when members can unstake without admin
+pub const MIN_EMERGENCY_GRACE_PERIOD: i64 = 1;
+pub const MAX_EMERGENCY_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60;
 
 
 #[program]
@@ -15,15 +27,29 @@ pub mod fctr_staking {
     
     // Initialize stake registrar
     #[access_control(Initialize::accounts(&ctx, nonce))]
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,      
         _bump: u8,
         mint: Pubkey,
         authority: Pubkey,
         nonce: u8,
-        round_timelock: i64,              
+        round_timelock: i64,
+        fctr_buy_course: u64,
+        fctr_sell_course: u64,
+        bcdev_sell_course: u64,
+        price_curve: PriceCurve,
     ) -> Result<()> {
 
+        // Check that courses are not zero
+        require!(
+            fctr_buy_course > 0 && fctr_sell_course > 0 && bcdev_sell_course > 0,
+            ErrorCode::InvalidCourse
+        );
+
+        // Check bonding curve parameters
+        require!(price_curve.is_valid(), ErrorCode::InvalidPriceCurve);
+
         // Create registrar struct
         let registrar = &mut ctx.accounts.registrar;
         registrar.authority = authority;
@@ -34,13 +60,190 @@ pub mod fctr_staking {
         registrar.round_timelock = round_timelock;
         registrar.current_round=0;
         registrar.current_round_account=None;      
+        registrar.fctr_buy_course = fctr_buy_course;
+        registrar.fctr_sell_course = fctr_sell_course;
+        registrar.bcdev_sell_course = bcdev_sell_course;
+        registrar.fctr_price_feed = None;
+        registrar.bcdev_price_feed = None;
+        registrar.max_price_age = 0;
+        registrar.max_price_confidence = 0;
+        registrar.price_curve = price_curve;
+        registrar.sol_vault_bump = *ctx.bumps.get("vault_sol_account").unwrap();
+        registrar.fee_bps = 0;
+        registrar.treasury = authority;
+        registrar.max_supply = None;
+        registrar.max_per_member = None;
+        registrar.merkle_root = None;
+        registrar.referral_bps = 0;
+        registrar.crank_tip = 0;
+        registrar.trading_paused = false;
+        registrar.staking_paused = false;
+        registrar.sharing_paused = false;
+        registrar.pending_authority = None;
+        registrar.emergency_grace_period = round_timelock
+            .clamp(MIN_EMERGENCY_GRACE_PERIOD, MAX_EMERGENCY_GRACE_PERIOD);
+        registrar.early_unstake_penalty_bps = 0;
+        registrar.penalty_mode = PenaltyMode::Burn;
+        registrar.quote_mint = None;
+        registrar.quote_vault = None;
+        registrar.quote_buy_price = 0;
+        registrar.quote_sell_price = 0;
+        registrar.quote_supply = 0;
+        registrar.sol_supply = 0;
         
         Ok(())
     }
+
+    // Change exchange courses by owner
+    pub fn set_prices(
+        ctx: Context<SetPrices>,
+        fctr_buy_course: u64,
+        fctr_sell_course: u64,
+        bcdev_sell_course: u64,
+    ) -> Result<()> {
+
+        // Check that courses are not zero
+        require!(
+            fctr_buy_course > 0 && fctr_sell_course > 0 && bcdev_sell_course > 0,
+            ErrorCode::InvalidCourse
+        );
+
+        // Change registrar course fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.fctr_buy_course = fctr_buy_course;
+        registrar.fctr_sell_course = fctr_sell_course;
+        registrar.bcdev_sell_course = bcdev_sell_course;
+
+        //Emit prices change event
+        emit!(PricesEventLog {
+            registrar: *ctx.accounts.registrar.to_account_info().key,
+            fctr_buy_course,
+            fctr_sell_course,
+            bcdev_sell_course,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Change trade fee and treasury by owner
+    pub fn set_fee(ctx: Context<SetFee>, fee_bps: u64, treasury: Pubkey) -> Result<()> {
+
+        // Check that fee is not more then 100%
+        require!(fee_bps <= BPS, ErrorCode::InvalidFee);
+
+        // Change registrar fee fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.fee_bps = fee_bps;
+        registrar.treasury = treasury;
+
+        Ok(())
+    }
+
+    // Change FCTR supply caps by owner
+    pub fn set_supply_caps(
+        ctx: Context<SetSupplyCaps>,
+        max_supply: Option<u64>,
+        max_per_member: Option<u64>,
+    ) -> Result<()> {
+
+        // Change registrar cap fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.max_supply = max_supply;
+        registrar.max_per_member = max_per_member;
+
+        Ok(())
+    }
+
+    // Change members allowlist root by owner
+    pub fn set_merkle_root(ctx: Context<SetMerkleRoot>, merkle_root: Option<[u8; 32]>) -> Result<()> {
+
+        // Change registrar allowlist root
+        ctx.accounts.registrar.merkle_root = merkle_root;
+
+        Ok(())
+    }
+
+    // Change referral bonus by owner
+    pub fn set_referral_bps(ctx: Context<SetReferralBps>, referral_bps: u64) -> Result<()> {
+
+        // Check that bonus is not more then 100%
+        require!(referral_bps <= BPS, ErrorCode::InvalidFee);
+
+        // Change registrar referral bonus
+        ctx.accounts.registrar.referral_bps = referral_bps;
+
+        Ok(())
+    }
+
+    // Create price feed account
+    pub fn create_price_feed(ctx: Context<CreatePriceFeed>, authority: Pubkey) -> Result<()> {
+
+        // Create price feed struct
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.authority = authority;
+        price_feed.course = 0;
+        price_feed.confidence = 0;
+        price_feed.publish_ts = 0;
+
+        Ok(())
+    }
+
+    // Publish new course to the price feed
+    pub fn update_price_feed(ctx: Context<UpdatePriceFeed>, course: u64, confidence: u64) -> Result<()> {
+
+        // Check that course is not zero
+        require!(course > 0, ErrorCode::InvalidCourse);
+
+        // Change price feed fields
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.course = course;
+        price_feed.confidence = confidence;
+        price_feed.publish_ts = ctx.accounts.clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    // Point registrar at price feeds by owner
+    pub fn set_price_feeds(
+        ctx: Context<SetPriceFeeds>,
+        fctr_price_feed: Option<Pubkey>,
+        bcdev_price_feed: Option<Pubkey>,
+        max_price_age: i64,
+        max_price_confidence: u64,
+    ) -> Result<()> {
+
+        // Change registrar price feed fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.fctr_price_feed = fctr_price_feed;
+        registrar.bcdev_price_feed = bcdev_price_feed;
+        registrar.max_price_age = max_price_age;
+        registrar.max_price_confidence = max_price_confidence;
+
+        Ok(())
+    }
    
     // Initialize member struct
     #[access_control(CreateMember::accounts(&ctx, nonce))]
-    pub fn create_member(ctx: Context<CreateMember>, nonce: u8, partisipate_sharing: bool) -> Result<()> {
+    pub fn create_member<'info>(
+        ctx: Context<'_,'_,'_,'info, CreateMember<'info>>,
+        nonce: u8,
+        partisipate_sharing: bool,
+        // Allowlist proof for beneficiary
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
+        
+        // Check that beneficiary is on allowlist if registrar has one
+        if let Some(merkle_root) = ctx.accounts.registrar.merkle_root {
+            let leaf = keccak::hash(ctx.accounts.beneficiary.key.as_ref()).0;
+            require!(
+                verify_merkle_proof(&proof, merkle_root, leaf),
+                ErrorCode::NotAllowlisted
+            );
+        }
         
         // Create member of staking struct
         let member = &mut ctx.accounts.member;
@@ -58,12 +261,51 @@ pub mod fctr_staking {
         member.dont_participate_sharing = partisipate_sharing; 
         member.nonce = nonce;                
         member.trusted_pool=vec![];
+        member.total_bought = 0;
+        member.referrer = None;
+        member.referred_members = 0;
+        member.referral_rewards = 0;
+
+        // Save referrer member if it is provided in remaining accounts
+        if let Some(referrer_info) = ctx.remaining_accounts.first() {
+            let mut referrer = Account::<Member>::try_from(referrer_info)?;
+
+            // Check that referrer is member of other beneficiary of the registrar
+            require!(
+                referrer.registrar == ctx.accounts.registrar.key() &&
+                    referrer.key() != ctx.accounts.member.key() &&
+                    referrer.beneficiary != ctx.accounts.beneficiary.key(),
+                ErrorCode::InvalidReferrer
+            );
+
+            // Increase referrer members counter
+            referrer.referred_members += 1;
+            referrer.exit(ctx.program_id)?;
+
+            ctx.accounts.member.referrer = Some(referrer.key());
+
+            //Emit referrer event
+            emit!(ReferrerSetEventLog {
+                referrer: referrer.key(),
+                member: ctx.accounts.member.key(),
+                referred_members: referrer.referred_members,
+                ts: ctx.accounts.clock.unix_timestamp,
+            });
+        }
 
         Ok(())
     }
 
     // Buy FCTR tokens
-    pub fn buy_fctr(ctx: Context<BuyFctr>, amount: u64) -> Result<()> {
+    pub fn buy_fctr<'info>(
+        ctx: Context<'_,'_,'_,'info, BuyFctr<'info>>,
+        amount: u64,
+        // Max lamports buyer agrees to pay
+        max_lamports: u64,
+    ) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
 
         // Check that member doesn't have trusters
         require!(
@@ -80,12 +322,48 @@ pub mod fctr_staking {
         //Check that  amount is more then 10 tokens        
         require!(amount >= 10*FCTR, ErrorCode::AmountTooSmall);       
         
+        // Check that purchase fits supply caps
+        check_supply_caps(
+            &ctx.accounts.registrar,
+            ctx.accounts.mint.supply,
+            ctx.accounts.member.total_bought,
+            amount,
+        )?;
+        
+        // Price feed goes first in remaining accounts and referrer accounts after it
+        let mut remaining_accounts = ctx.remaining_accounts;
+        
         // Calculate lamports amount for requested tokens
-        let sol_amount= curency_to_sol_in_lamports(&amount,&FCTR,&109);   
+        let sol_amount = if ctx.accounts.registrar.price_curve == PriceCurve::Flat {
+            // Get course from price feed or registrar
+            let course = feed_course(
+                &ctx.accounts.registrar,
+                &ctx.accounts.registrar.fctr_price_feed,
+                ctx.accounts.registrar.fctr_buy_course,
+                remaining_accounts,
+                &ctx.accounts.clock,
+            )?;
+            if ctx.accounts.registrar.fctr_price_feed.is_some() {
+                remaining_accounts = &remaining_accounts[1..];
+            }
+            curency_to_sol_in_lamports(&amount,&FCTR,&course)?
+        } else {
+            // Price the SOL backed supply growth along the bonding curve
+            let supply = ctx.accounts.registrar.sol_supply;
+            let new_supply = supply.checked_add(amount).ok_or(error!(ErrorCode::MathOverflow))?;
+            curve_lamports(&ctx.accounts.registrar.price_curve, supply, new_supply)?
+        };
         msg!("Sol amount for {} FCTR is {}",amount,sol_amount);
         
+        // Calculate fee for treasury on top of the price
+        let fee = fee_amount(sol_amount, ctx.accounts.registrar.fee_bps);
+        msg!("Fee amount is {}",fee);
+        
+        // Check that price didn't move over buyer bound
+        require!(sol_amount + fee <= max_lamports, ErrorCode::SlippageExceeded);
+        
         //Check that buyer has enouph lamports for transfer  
-        if **ctx.accounts.buyer.try_borrow_lamports()? < sol_amount  {
+        if **ctx.accounts.buyer.try_borrow_lamports()? < sol_amount + fee  {
             return Err(error!(ErrorCode::InsufficientFundsForTransaction));
         }
         
@@ -103,6 +381,22 @@ pub mod fctr_staking {
             ],
         )?;          
         
+        //Transfer fee to treasury
+        if fee > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.buyer.key(),
+                &ctx.accounts.treasury.key(),
+                fee,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.buyer.to_account_info(),
+                    ctx.accounts.treasury.to_account_info(),
+                ],
+            )?;
+        }
+        
         // Mint FCTR tokens amount to the buyer
         {
             let seeds = &[
@@ -125,12 +419,99 @@ pub mod fctr_staking {
 
         // Increase member bought counter for token amount
         ctx.accounts.member.bought+=amount;
+        ctx.accounts.member.total_bought+=amount;
+        ctx.accounts.registrar.sol_supply+=amount;
+        
+        // Mint referral bonus to the referrer
+        if let Some(referrer_key) = ctx.accounts.member.referrer {
+            if ctx.accounts.registrar.referral_bps > 0 {
+                
+                // Referrer member and its BCDEV vault go in remaining accounts
+                require!(remaining_accounts.len() >= 2, ErrorCode::LowRemainingAccountsProvided);
+                let mut referrer = Account::<Member>::try_from(&remaining_accounts[0])?;
+                let referrer_bcdev_vault = Account::<TokenAccount>::try_from(&remaining_accounts[1])?;
+                
+                // Check that accounts are equal to saved in Member struct
+                require!(referrer.key() == referrer_key, ErrorCode::InvalidReferrer);
+                let referrer_signer = Pubkey::create_program_address(
+                    &[
+                        ctx.accounts.registrar.to_account_info().key.as_ref(),
+                        referrer_key.as_ref(),
+                        &[referrer.nonce],
+                    ],
+                    ctx.program_id,
+                )
+                .map_err(|_| error!(ErrorCode::InvalidNonce))?;
+                require!(
+                    referrer_bcdev_vault.owner == referrer_signer &&
+                        referrer_bcdev_vault.mint == ctx.accounts.pool_mint.key(),
+                    ErrorCode::VaultWrongKey
+                );
+                
+                // Calculate BCDEV bonus redeemable for part of paid lamports by BCDEV course
+                let bonus = (sol_amount as u128)
+                    .checked_mul(ctx.accounts.registrar.referral_bps as u128)
+                    .and_then(|v| v.checked_mul(ctx.accounts.registrar.bcdev_sell_course as u128))
+                    .and_then(|v| v.checked_mul(BCDEV as u128))
+                    .ok_or(error!(ErrorCode::MathOverflow))?
+                    / (BPS as u128 * LAMPORTS_PER_SOL as u128);
+                let bonus = u64::try_from(bonus).map_err(|_| error!(ErrorCode::MathOverflow))?;
+                
+                // Mint BCDEV bonus to the referrer
+                {
+                    let seeds = &[
+                        ctx.accounts.registrar.to_account_info().key.as_ref(),
+                        &[ctx.accounts.registrar.nonce],
+                    ];
+                    let registrar_signer = &[&seeds[..]];
+
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info().clone(),
+                        token::MintTo {
+                            mint: ctx.accounts.pool_mint.to_account_info(),
+                            to: referrer_bcdev_vault.to_account_info(),
+                            authority: ctx.accounts.registrar_signer.to_account_info(),
+                        },
+                        registrar_signer,
+                    );
+                    token::mint_to(cpi_ctx, bonus)?;
+                }
+                
+                // Increase referrer rewards counter
+                referrer.referral_rewards += bonus;
+                referrer.exit(ctx.program_id)?;
+                
+                //Emit referral event
+                emit!(ReferralEventLog {
+                    referrer: referrer_key,
+                    member: *ctx.accounts.member.to_account_info().key,
+                    bonus,
+                    referred_members: referrer.referred_members,
+                    referral_rewards: referrer.referral_rewards,
+                    ts: ctx.accounts.clock.unix_timestamp,
+                });
+            }
+        }
+        
+        //Emit trade event
+        emit!(TradeEventLog {
+            member: *ctx.accounts.member.to_account_info().key,
+            mint: ctx.accounts.mint.key(),
+            buy: true,
+            amount,
+            lamports: sol_amount,
+            fee,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
         
         Ok(())
     }        
     
     // Transfer FCTR tokens without sharing 
     pub fn transfer_fctr(ctx: Context<TransferFctr>, amount: u64) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
                 
         //Check that user have enouph tokens for transfer
         require!(ctx.accounts.vault_fctr.amount >= amount, ErrorCode::InsuficientUserFunds);
@@ -164,23 +545,48 @@ pub mod fctr_staking {
     }    
 
     // Sell some BCDEV amount
-    pub fn sell_bcdev(ctx: Context<SellBcdev>, amount: u64) -> Result<()> {
+    pub fn sell_bcdev<'info>(
+        ctx: Context<'_,'_,'_,'info, SellBcdev<'info>>,
+        amount: u64,
+        // Min lamports seller agrees to receive
+        min_lamports: u64,
+    ) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
         
         // Check that user have enouph tokens for transfer
         require!(ctx.accounts.vault_bcdev.amount >= amount, ErrorCode::InsuficientUserFunds);
         
+        // Get course from price feed or registrar
+        let course = feed_course(
+            &ctx.accounts.registrar,
+            &ctx.accounts.registrar.bcdev_price_feed,
+            ctx.accounts.registrar.bcdev_sell_course,
+            ctx.remaining_accounts,
+            &ctx.accounts.clock,
+        )?;
+        
         // Calculate lamports amount for BCDEV tokens amount
-        let sol_amount= curency_to_sol_in_lamports(&amount,&BCDEV,&11);   
+        let sol_amount= curency_to_sol_in_lamports(&amount,&BCDEV,&course)?;   
         msg!("Sol amount for {} BCDEV is {}",amount,sol_amount);        
         
+        // Calculate fee for treasury out of the price
+        let fee = fee_amount(sol_amount, ctx.accounts.registrar.fee_bps);
+        msg!("Fee amount is {}",fee);
+        
+        // Check that price didn't move under seller bound
+        require!(sol_amount - fee >= min_lamports, ErrorCode::SlippageExceeded);
+        
         //Check that vault has enouph lamports for transfer  
         if **ctx.accounts.vault_sol_account.try_borrow_lamports()? < sol_amount  {
             return Err(error!(ErrorCode::InsufficientFundsForTransaction));
         }     
    
-        // Transfer lamports to member
+        // Transfer lamports to member and fee to treasury
         **ctx.accounts.vault_sol_account.try_borrow_mut_lamports()? -= sol_amount ;
-        **ctx.accounts.beneficiary.try_borrow_mut_lamports()? += sol_amount ;  
+        **ctx.accounts.beneficiary.try_borrow_mut_lamports()? += sol_amount - fee ;  
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += fee ;
         
         // Burn seller BCDEV tokens        
         let seeds = &[
@@ -202,50 +608,248 @@ pub mod fctr_staking {
             token::burn(cpi_ctx, amount)?;
         }       
         
+        //Emit trade event
+        emit!(TradeEventLog {
+            member: *ctx.accounts.member.to_account_info().key,
+            mint: ctx.accounts.pool_mint.key(),
+            buy: false,
+            amount,
+            lamports: sol_amount,
+            fee,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+        
         Ok(())
     }
     
     // Sell all FCTR tokens
-    pub fn sell_all_fctr(ctx: Context<SellFctr>) -> Result<()> {
+    pub fn sell_all_fctr(
+        ctx: Context<SellFctr>,
+        // Min lamports seller agrees to receive
+        min_lamports: u64,
+    ) -> Result<()> {
+        
+        // Get having amount of tokens   
+        let amount = ctx.accounts.member.bought;
+        
+        sell_fctr_tokens(ctx.accounts, amount, min_lamports)
+    }
+    
+    // Sell some FCTR amount
+    pub fn sell_fctr(
+        ctx: Context<SellFctr>,
+        amount: u64,
+        // Min lamports seller agrees to receive
+        min_lamports: u64,
+    ) -> Result<()> {
+        
+        // Check that amount is not zero
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+        
+        // Check that member has enouph bought tokens
+        require!(ctx.accounts.member.bought >= amount, ErrorCode::InsuficientUserFunds);
+        
+        sell_fctr_tokens(ctx.accounts, amount, min_lamports)
+    }
+    
+    // Accept SPL quote token for FCTR by owner
+    pub fn set_quote_mint(
+        ctx: Context<SetQuoteMint>,
+        quote_buy_price: u64,
+        quote_sell_price: u64,
+    ) -> Result<()> {
 
-            // Check that member has no trusters
+        // Check that prices are not zero
         require!(
-            ctx.accounts.member.trusted_pool.len()==0,
+            quote_buy_price > 0 && quote_sell_price > 0,
+            ErrorCode::InvalidCourse
+        );
+
+        // Check that quote vault changes only without quote supply
+        if ctx.accounts.registrar.quote_vault != Some(ctx.accounts.quote_vault.key()) {
+            require!(
+                ctx.accounts.registrar.quote_supply == 0,
+                ErrorCode::ThereIsSomeSupply
+            );
+        }
+
+        // Change registrar quote fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.quote_mint = Some(ctx.accounts.quote_mint.key());
+        registrar.quote_vault = Some(ctx.accounts.quote_vault.key());
+        registrar.quote_buy_price = quote_buy_price;
+        registrar.quote_sell_price = quote_sell_price;
+
+        Ok(())
+    }
+
+    // Buy FCTR tokens for quote tokens
+    pub fn buy_fctr_with_quote(
+        ctx: Context<BuyFctrWithQuote>,
+        amount: u64,
+        // Max quote tokens buyer agrees to pay
+        max_quote_amount: u64,
+    ) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
+
+        // Check that member doesn't have trusters
+        require!(
+            ctx.accounts.member.trusted_pool.is_empty(),
             ErrorCode::CantBuyOfSharing,
         );
-        
+
+        // Check that member didn't shared
+        require!(
+            ctx.accounts.member.shared==0,
+            ErrorCode::CantBuyOfSharing,
+        );
+
+        //Check that  amount is more then 10 tokens
+        require!(amount >= 10*FCTR, ErrorCode::AmountTooSmall);
+
+        // Check that purchase fits supply caps
+        check_supply_caps(
+            &ctx.accounts.registrar,
+            ctx.accounts.mint.supply,
+            ctx.accounts.member.total_bought,
+            amount,
+        )?;
+
+        // Calculate quote amount for requested tokens rounded up
+        let quote_amount = fctr_to_quote(amount, ctx.accounts.registrar.quote_buy_price, true)?;
+        msg!("Quote amount for {} FCTR is {}",amount,quote_amount);
+
+        // Check that price didn't move over buyer bound
+        require!(quote_amount <= max_quote_amount, ErrorCode::SlippageExceeded);
+
+        //Check that buyer has enouph quote tokens for transfer
+        require!(
+            ctx.accounts.buyer_quote_account.amount >= quote_amount,
+            ErrorCode::InsufficientFundsForTransaction
+        );
+
+        // Transfer quote tokens to registrar
+        {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.buyer_quote_account.to_account_info(),
+                    to: ctx.accounts.quote_vault.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, quote_amount)?;
+        }
+
+        // Mint FCTR tokens amount to the buyer
+        {
+            let seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                &[ctx.accounts.registrar.nonce],
+            ];
+            let registrar_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
+                },
+                registrar_signer,
+            );
+            token::mint_to(cpi_ctx, amount)?;
+        }
+
+        // Increase member bought counter for token amount
+        ctx.accounts.member.bought+=amount;
+        ctx.accounts.member.total_bought+=amount;
+
+        // Increase FCTR supply backed by quote tokens
+        ctx.accounts.registrar.quote_supply+=amount;
+
+        Ok(())
+    }
+
+    // Sell some FCTR amount for quote tokens
+    pub fn sell_fctr_for_quote(
+        ctx: Context<SellFctrForQuote>,
+        amount: u64,
+        // Min quote tokens seller agrees to receive
+        min_quote_amount: u64,
+    ) -> Result<()> {
+
+        // Check that trading is not paused
+        require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
+
+        // Check that member has no trusters
+        require!(
+            ctx.accounts.member.trusted_pool.is_empty(),
+            ErrorCode::CantBuyOfSharing,
+        );
+
         // Check that member didn't share
         require!(
             ctx.accounts.member.shared==0,
             ErrorCode::CantBuyOfSharing,
         );
-        
-        // Get having amount of tokens   
-        let amount = ctx.accounts.member.bought;
-        
-        // Calculate lamports amount for tokens
-        let sol_amount= curency_to_sol_in_lamports(&amount,&FCTR,&101);   
-        msg!("Sol amount for {} FCTR is {}",amount,sol_amount);        
-        
-        // Check that vault has enouph lamports for transfer  
-        if **ctx.accounts.vault_sol_account.try_borrow_lamports()? < sol_amount  {
-            return Err(error!(ErrorCode::InsufficientFundsForTransaction));
+
+        // Check that amount is not zero
+        require!(amount > 0, ErrorCode::AmountTooSmall);
+
+        // Check that member has enouph bought tokens
+        require!(ctx.accounts.member.bought >= amount, ErrorCode::InsuficientUserFunds);
+
+        // Check that tokens were bought for quote tokens
+        require!(
+            ctx.accounts.registrar.quote_supply >= amount,
+            ErrorCode::QuoteSupplyExceeded
+        );
+
+        // Calculate quote amount for tokens
+        let quote_amount = fctr_to_quote(amount, ctx.accounts.registrar.quote_sell_price, false)?;
+        msg!("Quote amount for {} FCTR is {}",amount,quote_amount);
+
+        // Check that price didn't move under seller bound
+        require!(quote_amount >= min_quote_amount, ErrorCode::SlippageExceeded);
+
+        // Check that vault has enouph quote tokens for transfer
+        require!(
+            ctx.accounts.quote_vault.amount >= quote_amount,
+            ErrorCode::InsufficientFundsForTransaction
+        );
+
+        // Transfer quote tokens to member
+        {
+            let seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                &[ctx.accounts.registrar.nonce],
+            ];
+            let registrar_signer = &[&seeds[..]];
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.quote_vault.to_account_info(),
+                    to: ctx.accounts.beneficiary_quote_account.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
+                },
+                registrar_signer,
+            );
+            token::transfer(cpi_ctx, quote_amount)?;
         }
-        
-        // Transfer amount to member
-        **ctx.accounts.vault_sol_account.try_borrow_mut_lamports()? -= sol_amount ;
-        **ctx.accounts.beneficiary.try_borrow_mut_lamports()? += sol_amount ;  
-        
-        // Burn seller FCTR tokens         
-        let seeds = &[
+
+        // Burn seller FCTR tokens
+        {
+            let seeds = &[
                 ctx.accounts.registrar.to_account_info().key.as_ref(),
                 ctx.accounts.member.to_account_info().key.as_ref(),
                 &[ctx.accounts.member.nonce],
             ];
             let member_signer = &[&seeds[..]];
 
-        // Burn pool tokens.
-        {
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info().clone(),
                 token::Burn {
@@ -256,11 +860,14 @@ pub mod fctr_staking {
                 member_signer,
             );
             token::burn(cpi_ctx, amount)?;
-        }       
+        }
+
+        // Decrease having amount of tokens
+        ctx.accounts.member.bought -= amount;
+
+        // Decrease FCTR supply backed by quote tokens
+        ctx.accounts.registrar.quote_supply -= amount;
 
-        // Zero having amount of tokens        
-        ctx.accounts.member.bought=0;
-        
         Ok(())
     }
     
@@ -269,24 +876,40 @@ pub mod fctr_staking {
         ctx: Context<StartRound>,
         // Mark round as final
         final_round: bool, 
+        // Round duration in seconds
+        duration: i64,
+        // Base APR in percents multiplied 100000
+        base_apr: u64,
+        // APR bonus for each truster in percents multiplied 100000
+        truster_apr: u64,
+        // BCDEV budget distributed by stake shares instead of APR
+        reward_budget: Option<u64>,
     ) -> Result<()> {
 
         // Check that round is not finalized  
         require!(!ctx.accounts.registrar.finalized, ErrorCode::StakingFinalized);
         
-        // Create round struct
-        let round = &mut ctx.accounts.round;
-        round.registrar = *ctx.accounts.registrar.to_account_info().key;
-        round.start_ts=ctx.accounts.clock.unix_timestamp;
-        round.stop_ts=ctx.accounts.clock.unix_timestamp +
-            ctx.accounts.registrar.round_timelock;
-        round.number=ctx.accounts.registrar.current_round + 1;
-        round.final_round=final_round;
-
-        // Change registrar round fields
-        ctx.accounts.registrar.current_round+=1;
-        ctx.accounts.registrar.current_round_account=Some(round.key());
-        ctx.accounts.registrar.finalized=final_round;
+        // Check that round has duration
+        require!(duration > 0, ErrorCode::InvalidRoundDuration);
+        
+        // Create round struct and change registrar round fields
+        let registrar_key = ctx.accounts.registrar.key();
+        let round_key = ctx.accounts.round.key();
+        open_round(
+            &mut ctx.accounts.registrar,
+            registrar_key,
+            &mut ctx.accounts.round,
+            round_key,
+            ctx.accounts.clock.unix_timestamp,
+            final_round,
+            duration,
+            base_apr,
+            truster_apr,
+            reward_budget,
+        );
+        
+        // Save who paid rent for the round
+        ctx.accounts.round.payer = ctx.accounts.authority.key();
                 
         //Emit round start event
         emit!(RoundEventLog {
@@ -297,8 +920,201 @@ pub mod fctr_staking {
         Ok(())
     }
     
-    // Stake to the round by member
-    pub fn stake( ctx: Context<Stake>) -> Result<()> {
+    // Start next staking round by anyone after the current one is over
+    pub fn crank_round(ctx: Context<CrankRound>) -> Result<()> {
+
+        // Check that staking is not paused
+        require!(!ctx.accounts.registrar.staking_paused, ErrorCode::ProgramPaused);
+
+        // Check that round is not finalized
+        require!(!ctx.accounts.registrar.finalized, ErrorCode::StakingFinalized);
+
+        // Check that current round is over
+        require!(
+            ctx.accounts.current_round.stop_ts <
+                ctx.accounts.clock.unix_timestamp,
+            ErrorCode::RoundIsNotOver
+        );
+
+        // Create round struct with registrar timelock and current round APR
+        let registrar_key = ctx.accounts.registrar.key();
+        let round_key = ctx.accounts.round.key();
+        let duration = ctx.accounts.registrar.round_timelock;
+        open_round(
+            &mut ctx.accounts.registrar,
+            registrar_key,
+            &mut ctx.accounts.round,
+            round_key,
+            ctx.accounts.clock.unix_timestamp,
+            false,
+            duration,
+            ctx.accounts.current_round.base_apr,
+            ctx.accounts.current_round.truster_apr,
+            ctx.accounts.current_round.reward_budget,
+        );
+        
+        // Save who paid rent for the round
+        ctx.accounts.round.payer = ctx.accounts.cranker.key();
+
+        // Pay tip to cranker from tips vault, SOL vault only backs tokens
+        let tip = ctx.accounts.registrar.crank_tip;
+        let vault_lamports = **ctx.accounts.crank_tip_vault.try_borrow_lamports()?;
+        let vault_rent = ctx.accounts.rent.minimum_balance(0);
+        if tip > 0 {
+            if vault_lamports >= vault_rent + tip {
+                let registrar_key = ctx.accounts.registrar.key();
+                let seeds = &[
+                    b"crank-seed".as_ref(),
+                    registrar_key.as_ref(),
+                    &[*ctx.bumps.get("crank_tip_vault").unwrap()],
+                ];
+                let ix = anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.crank_tip_vault.key(),
+                    &ctx.accounts.cranker.key(),
+                    tip,
+                );
+                invoke_signed(
+                    &ix,
+                    &[
+                        ctx.accounts.crank_tip_vault.to_account_info(),
+                        ctx.accounts.cranker.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    &[&seeds[..]],
+                )?;
+            } else {
+                msg!("Not enouph lamports in vault for crank tip");
+            }
+        }
+
+        //Emit round start event
+        emit!(RoundEventLog {
+            round: *ctx.accounts.round.to_account_info().key,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Change crank tip by owner
+    pub fn set_crank_tip(ctx: Context<SetCrankTip>, crank_tip: u64) -> Result<()> {
+
+        // Change registrar crank tip
+        ctx.accounts.registrar.crank_tip = crank_tip;
+
+        Ok(())
+    }
+    
+    // Nominate new registrar owner by owner
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+
+        // Save nominated authority
+        ctx.accounts.registrar.pending_authority = Some(new_authority);
+
+        //Emit authority proposal event
+        emit!(AuthorityProposedEventLog {
+            registrar: ctx.accounts.registrar.key(),
+            authority: ctx.accounts.authority.key(),
+            pending_authority: new_authority,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Accept registrar ownership by nominated authority
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+
+        // Move ownership to nominated authority
+        let registrar = &mut ctx.accounts.registrar;
+        let old_authority = registrar.authority;
+        registrar.authority = ctx.accounts.new_authority.key();
+        registrar.pending_authority = None;
+
+        //Emit authority acceptance event
+        emit!(AuthorityAcceptedEventLog {
+            registrar: registrar.key(),
+            old_authority,
+            new_authority: registrar.authority,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+    
+    // Change emergency unstake grace period by owner
+    pub fn set_emergency_grace_period(
+        ctx: Context<SetEmergencyGracePeriod>,
+        emergency_grace_period: i64,
+    ) -> Result<()> {
+
+        // Check that grace period keeps stuck round path enabled
+        require!(
+            (MIN_EMERGENCY_GRACE_PERIOD..=MAX_EMERGENCY_GRACE_PERIOD)
+                .contains(&emergency_grace_period),
+            ErrorCode::InvalidGracePeriod
+        );
+
+        // Change registrar grace period
+        ctx.accounts.registrar.emergency_grace_period = emergency_grace_period;
+
+        Ok(())
+    }
+
+    // Change early unstake penalty by owner
+    pub fn set_early_unstake_penalty(
+        ctx: Context<SetEarlyUnstakePenalty>,
+        penalty_bps: u64,
+        penalty_mode: PenaltyMode,
+    ) -> Result<()> {
+
+        // Check that penalty is not more then 100%
+        require!(penalty_bps <= BPS, ErrorCode::InvalidPenalty);
+
+        // Change registrar penalty fields
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.early_unstake_penalty_bps = penalty_bps;
+        registrar.penalty_mode = penalty_mode;
+
+        Ok(())
+    }
+    
+    // Pause or resume trading, staking and sharing by owner
+    pub fn set_pause(
+        ctx: Context<SetPause>,
+        trading_paused: bool,
+        staking_paused: bool,
+        sharing_paused: bool,
+    ) -> Result<()> {
+
+        // Change registrar pause flags
+        let registrar = &mut ctx.accounts.registrar;
+        registrar.trading_paused = trading_paused;
+        registrar.staking_paused = staking_paused;
+        registrar.sharing_paused = sharing_paused;
+
+        //Emit pause event
+        emit!(PauseEventLog {
+            registrar: ctx.accounts.registrar.key(),
+            trading_paused,
+            staking_paused,
+            sharing_paused,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+    
+    // Stake to the round by member
+    pub fn stake(
+        ctx: Context<Stake>,
+        amount: u64,
+        // Which tokens are staked first
+        allocation: StakeAllocation,
+    ) -> Result<()> {
+
+        // Check that staking is not paused
+        require!(!ctx.accounts.registrar.staking_paused, ErrorCode::ProgramPaused);
 
         //Check that member is staking to current round
         require!(
@@ -314,14 +1130,42 @@ pub mod fctr_staking {
             ErrorCode::RoundTimeIsOverStaking
         );
         
-        // Check member has tokens
-        require!(ctx.accounts.member.bought > 0 ,
+        // Check member has own or trusted tokens
+        require!(
+            ctx.accounts.member.bought > ctx.accounts.member.shared ||
+                ctx.accounts.member.trusted > 0,
             ErrorCode::NotEnouphForStake,            
         );
         
-        // Get free tokens amount
-        let token_amount = ctx.accounts.member.bought - ctx.accounts.member.shared +
-                    ctx.accounts.member.trusted;        
+        // Get free own and trusted tokens amount
+        let own_free = ctx.accounts.member.bought - ctx.accounts.member.shared;
+        let trusted_free = ctx.accounts.member.trusted;
+        
+        // Check that member has enouph free tokens
+        require!(
+            amount > 0 && amount <= own_free + trusted_free,
+            ErrorCode::NotEnouphForStake
+        );
+        
+        // Split staking amount by allocation rule
+        let (own_part, trusted_part) = match allocation {
+            StakeAllocation::TrustedFirst => {
+                let trusted_part = amount.min(trusted_free);
+                (amount - trusted_part, trusted_part)
+            }
+            StakeAllocation::OwnFirst => {
+                let own_part = amount.min(own_free);
+                (own_part, amount - own_part)
+            }
+        };
+        let token_amount = amount;
+        
+        // Check that trusted tokens are staked only in one round position
+        require!(
+            trusted_part == 0 ||
+                ctx.accounts.member.staked_trusted == ctx.accounts.position.trusted_amount,
+            ErrorCode::TrustedStakedInOtherRound
+        );
 
         // Transfer tokens into the registrar stake vault.
         {
@@ -346,51 +1190,60 @@ pub mod fctr_staking {
         //Calculate time for what current stake would be
         let staked_time = ctx.accounts.round.stop_ts - ctx.accounts.clock.unix_timestamp;
                 
-        //Add 2 percents multiplied 100000 for each truster to APR
-        let mut add_apr = 0;
-        for _ in ctx.accounts.member.trusted_pool.iter(){
-            add_apr+=2 * ACCURACY;
-        }
-        
-        // Calculate increase of base APR in % multiplied 100000 for member who trust his tokens
-        let x_apr: u64;
-        if ctx.accounts.member.shared == 0 {
-            // 1% * 100000 if not shared
-            x_apr = 100_000;
-        } else {
-            // Or calculated in percents * 100000
-            x_apr = part_calculation_x10_5(
-                &(ctx.accounts.member.bought + ctx.accounts.member.trusted -ctx.accounts.member.shared),
-                &ctx.accounts.member.shared,
-                &ACCURACY
-            );         
-        }        
-        msg!("apr {} with add {}",x_apr, add_apr);
+        // Calculate member APR in % multiplied 100000 for the round
+        let apr = stake_apr(&ctx.accounts.member, &ctx.accounts.round);
+        msg!("apr {}",apr);
 
         //Calculate stake pool tokens amount for the calculated time with calculated percents
         let spt_amount = apr_calculation (
             &token_amount,            
-            &(x_apr  + add_apr), 
-            &ctx.accounts.registrar.round_timelock,
+            &apr, 
+            &ctx.accounts.round.duration,
             &staked_time,
         );
         msg!("spt_amount {}",spt_amount);        
         
-        msg!("{} % part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.registrar.round_timelock as f64, token_amount, spt_amount);
+        msg!("{} % part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.round.duration as f64, token_amount, spt_amount);
     
+        // Update round statistics and count member once per round
+        let position = &mut ctx.accounts.position;
+        if position.amount == 0 && position.trusted_amount == 0 {
+            ctx.accounts.round.stakers += 1;
+            ctx.accounts.round.active_stakers += 1;
+            position.member = ctx.accounts.member.key();
+            position.round = ctx.accounts.round.key();
+            position.bump = *ctx.bumps.get("position").unwrap();
+        }
+        ctx.accounts.round.total_staked += own_part;
+        ctx.accounts.round.total_trusted_staked += trusted_part;
+        if ctx.accounts.round.reward_budget.is_none() {
+            ctx.accounts.round.reward_promised += spt_amount;
+        }
+
         //Increase stake value for member
-        ctx.accounts.member.staked += ctx.accounts.member.bought - ctx.accounts.member.shared;
+        ctx.accounts.member.staked += own_part;
 
         //Increase trusted stake value for member
-        ctx.accounts.member.staked_trusted += ctx.accounts.member.trusted;
+        ctx.accounts.member.staked_trusted += trusted_part;
         
-        //Null the member legitim amount to prevent double staking
-        ctx.accounts.member.bought = 0;
-        ctx.accounts.member.trusted = 0;
+        //Decrease the member free amounts to prevent double staking
+        ctx.accounts.member.bought -= own_part;
+        ctx.accounts.member.trusted -= trusted_part;
         
         // Increase APR reward with calculated amount
         ctx.accounts.member.reward += spt_amount; 
 
+        // Add time weighted shares to member and round
+        let shares = stake_shares(token_amount, staked_time);
+        ctx.accounts.member.shares += shares;
+        ctx.accounts.round.total_shares += shares;
+        
+        // Save stake to the round position
+        position.amount += own_part;
+        position.trusted_amount += trusted_part;
+        position.reward += spt_amount;
+        position.shares += shares;
+
         // Mark member stake status and staked round      
         ctx.accounts.member.unstaked = false;
         ctx.accounts.member.staked_round = Some(ctx.accounts.round.key());
@@ -415,13 +1268,21 @@ pub mod fctr_staking {
             ErrorCode::RoundTimeIsOverUnstaking
         );
 
-        // Check that member staked amount
-        require!(ctx.accounts.member.staked > 0 ,
+        // Check that position has own or trusted amount
+        require!(
+            ctx.accounts.position.amount > 0 || ctx.accounts.position.trusted_amount > 0,
             ErrorCode::DidntStakedAnything,            
         );
+        
+        // Get position reward or the position part of round budget
+        let reward = position_reward(&ctx.accounts.round, &ctx.accounts.position)?;
+        
+        // Check if trusters are settled by this position
+        let with_trusters = ctx.accounts.position.trusted_amount > 0 &&
+            !ctx.accounts.member.trusted_pool.is_empty();
 
-        // Get number of members staked tokens
-        let token_amount = ctx.accounts.member.staked;        
+        // Get number of members staked tokens in position
+        let token_amount = ctx.accounts.position.amount;        
         
         // Calculate registrar sign
         let seeds = &[
@@ -446,10 +1307,10 @@ pub mod fctr_staking {
         
         // Calculate 50% of reward for member if he trusted
         let spt_amount_member: u64;
-        if ctx.accounts.member.trusted_pool.len()>0 {
-                spt_amount_member = ctx.accounts.member.reward/2;
+        if with_trusters {
+                spt_amount_member = reward/2;
         } else {
-            spt_amount_member = ctx.accounts.member.reward
+            spt_amount_member = reward
         }
         
         // Mint pool tokens to the staker.
@@ -465,9 +1326,10 @@ pub mod fctr_staking {
             );
             token::mint_to(cpi_ctx, spt_amount_member)?;
         }                
+        ctx.accounts.round.reward_minted += spt_amount_member;
         
         //Check that member has trusters for stake
-        if ctx.accounts.member.trusted_pool.len() > 0 {
+        if with_trusters {
 
             //Check the provided remaining account for unstake and reward for trusters
             let provided_remaining_accounts = &mut ctx.remaining_accounts.iter();
@@ -481,12 +1343,29 @@ pub mod fctr_staking {
             
             // Calculate and distribute stake and APR with trusters
             // Calculate and distribute stake
-            let spt_amount_trusters = ctx.accounts.member.reward - spt_amount_member;
+            let spt_amount_trusters = reward - spt_amount_member;
             msg!("spt_amount_trusters {}",spt_amount_trusters);
             
-            // Calculate total stake for trasters
-            let total = ctx.accounts.member.staked_trusted;
+            // Calculate total trusted amount for trasters
+            let total: u64 = ctx.accounts.member.trusted_pool
+                .iter()
+                .map(|m_t_m| m_t_m.trusted_amount)
+                .sum();
             msg!("total {}",total);
+            
+            // Calculate trusters parts staked in registrar vault
+            let staked_parts = split_staked_trusted(
+                &ctx.accounts.member.trusted_pool,
+                ctx.accounts.position.trusted_amount,
+            );
+            
+            // Calculate member sign for not staked trusted tokens
+            let member_seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                ctx.accounts.member.to_account_info().key.as_ref(),
+                &[ctx.accounts.member.nonce],
+            ];
+            let member_signer = &[&member_seeds[..]];
 
             // Calculate parts for each truster
             let mut spt_part: u64;
@@ -505,8 +1384,9 @@ pub mod fctr_staking {
                 require!(vault_fctr.key== &ctx.accounts.member.trusted_pool[i/2].fctr, ErrorCode::VaultWrongKey);
                 require!(vault_bcdev.key== &ctx.accounts.member.trusted_pool[i/2].bcdev, ErrorCode::VaultWrongKey);
                 
-                // Transfer FCTR back to truster
-                {
+                // Transfer staked FCTR back to truster
+                let staked_part = staked_parts[i/2];
+                if staked_part > 0 {
                     let cpi_ctx = CpiContext::new_with_signer(
                         ctx.accounts.token_program.to_account_info().clone(),
                         token::Transfer {
@@ -516,7 +1396,22 @@ pub mod fctr_staking {
                         },
                         registrar_signer,
                     );                    
-                    token::transfer(cpi_ctx, ctx.accounts.member.trusted_pool[i/2].trusted_amount)?;
+                    token::transfer(cpi_ctx, staked_part)?;
+                }
+                
+                // Transfer not staked FCTR back to truster from member
+                let free_part = ctx.accounts.member.trusted_pool[i/2].trusted_amount - staked_part;
+                if free_part > 0 {
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info().clone(),
+                        token::Transfer {
+                            from: ctx.accounts.member_fctr_vault.to_account_info(),
+                            to: ctx.remaining_accounts[i].to_account_info(),
+                            authority: ctx.accounts.member_signer.to_account_info(),
+                        },
+                        member_signer,
+                    );
+                    token::transfer(cpi_ctx, free_part)?;
                 }
 
                 // Calculate reward multiplied 100000 part for truster
@@ -548,6 +1443,7 @@ pub mod fctr_staking {
                     );
                     token::mint_to(cpi_ctx, spt_part * spt_reduced)?;                    
                 }
+                ctx.accounts.round.reward_minted += spt_part * spt_reduced;
                                
                 // Zero truster stake amount
                 ctx.accounts.member.trusted_pool[i/2].trusted_amount=0;  
@@ -557,18 +1453,12 @@ pub mod fctr_staking {
             }                    
         }
 
-        // Zero members reward
-        ctx.accounts.member.reward = 0;
-        // Restore members bought amount
-        ctx.accounts.member.bought = token_amount;
-        // Zero member trusters pool
-        ctx.accounts.member.trusted_pool = vec![];
-        // Zero staking counters
-        ctx.accounts.member.trusted = 0;
-        ctx.accounts.member.staked = 0;
-        ctx.accounts.member.staked_trusted = 0;
-        // Mark member as unstaked
-        ctx.accounts.member.unstaked = true;    
+        // Release member from the round
+        ctx.accounts.round.active_stakers =
+            ctx.accounts.round.active_stakers.saturating_sub(1);
+
+        // Settle position in members counters
+        settle_position(&mut ctx.accounts.member, &ctx.accounts.position, with_trusters);
         
         //Emit reward event
         emit!(RewardEventLog {
@@ -579,653 +1469,1845 @@ pub mod fctr_staking {
         Ok(())
     }
 
-    // Trust some amount to stake member
-    pub fn trust_to_member( ctx: Context<TrustToMember>,amount:u64) -> Result<()> {
-    
-        // Check participating in sharing programm flag 
-        require!(
-            !ctx.accounts.member_to_trust.dont_participate_sharing,
-            ErrorCode::MemberDontParticipateSharing
-        );
+    // Settle ended round position and roll its principal into the current round
+    pub fn restake<'info>(
+        ctx: Context<'_,'_,'_,'info, Restake<'info>>,
+        // Convert BCDEV reward to FCTR and stake it too
+        compound_reward: bool,
+    ) -> Result<()> {
 
-        // Check that member shares less then once in 30 times round periods
-        require!(
-            ctx.accounts.clock.unix_timestamp -
-                ctx.accounts.truster_to_member.last_time_trusted >                
-                ctx.accounts.registrar.round_timelock * 30,
-            ErrorCode::OftenSharingParticipation
-        );
-        
-        // Sharing only bought tokens
+        // Check that staking is not paused
+        require!(!ctx.accounts.registrar.staking_paused, ErrorCode::ProgramPaused);
+
+        // Check that previous round is ended
         require!(
-            amount < ctx.accounts.member_who_trust.bought,
-            ErrorCode::ShareOnlyBought
+            ctx.accounts.registrar.current_round >
+                ctx.accounts.old_round.number &&
+                ctx.accounts.old_round.stop_ts <
+                    ctx.accounts.clock.unix_timestamp,
+            ErrorCode::EarlyUnstaking
         );
-        
-        //Check that user has les then four trusters
+
+        //Check that current round still goes on
         require!(
-            ctx.accounts.member_to_trust.trusted_pool.len() < 4,
-            ErrorCode::MemberTrustersAmountExceeded
+            ctx.accounts.round.stop_ts >
+                ctx.accounts.clock.unix_timestamp,
+            ErrorCode::RoundTimeIsOverStaking
         );
 
-        // Sharing amount must be less then the half of having
+        // Check that position has own amount
         require!(
-            amount < (ctx.accounts.member_who_trust.bought - ctx.accounts.member_who_trust.shared + ctx.accounts.member_who_trust.trusted)/2,
-            ErrorCode::AmountMoreThenHalf
+            ctx.accounts.old_position.amount > 0,
+            ErrorCode::DidntStakedAnything
         );
 
-        // Sharing amount must be less quater of bought 
-        require!(
-             (ctx.accounts.member_who_trust.bought -ctx.accounts.member_who_trust.shared + ctx.accounts.member_who_trust.trusted) - amount > ctx.accounts.member_who_trust.bought/4,
-             ErrorCode::AmountLesThenQuater
-        );                
-        
-        //Sharing or shared member must be between half and double of self deposit
-        let mut who = 0;
-        let mut to = 0;
-        
-        // Set amount for staked or unstaked status
-        if ctx.accounts.member_who_trust.bought != 0 {
-            who = ctx.accounts.member_who_trust.bought;
-        }else if ctx.accounts.member_who_trust.staked != 0 {
-            who = ctx.accounts.member_who_trust.staked;
-        }
-        
-        // Set amount for staked or unstaked status
-        if ctx.accounts.member_to_trust.bought != 0 {
-            to = ctx.accounts.member_to_trust.bought;
-        }else if ctx.accounts.member_to_trust.staked != 0 {
-            to = ctx.accounts.member_to_trust.staked;
-        }        
-        
-        //Sharing or shared member must be between half and double of self deposit 
+        // Trusters are settled only by unstake
         require!(
-            (who >= to/2) &&
-            (who <= to*2),
-            ErrorCode::AmountBetwenHalfAndDouble
+            ctx.accounts.old_position.trusted_amount == 0,
+            ErrorCode::RestakeWithTrusters
         );
-        
-        //Sharing or shared member must be between half and double of self deposit 
-        require!(
-            (to >= who/2) &&
-            (to <= who*2),
-            ErrorCode::AmountBetwenHalfAndDouble
-        );       
-         
-        // Check if user already unstaked in this round and return tokens if so
-        let unstaked =
-            ctx.accounts.member_to_trust.unstaked &&
-                ctx.accounts.member_to_trust.staked_round ==    
-                Some(ctx.accounts.round.key());        
-       
-        // Transfer trusted tokens to the memeber.
-        {
-            let seeds = &[
-                ctx.accounts.registrar.to_account_info().key.as_ref(),
-                ctx.accounts.member_who_trust.to_account_info().key.as_ref(),
-                &[ctx.accounts.member_who_trust.nonce],
-            ];
-            let member_signer = &[&seeds[..]];
+
+        // Get position reward or the position part of round budget
+        let reward = position_reward(&ctx.accounts.old_round, &ctx.accounts.old_position)?;
+
+        // Calculate registrar sign
+        let seeds = &[
+            ctx.accounts.registrar.to_account_info().key.as_ref(),
+            &[ctx.accounts.registrar.nonce],
+        ];
+        let registrar_signer = &[&seeds[..]];
+
+        // Mint reward as FCTR to the stake vault or as BCDEV to the member
+        let mut compounded = 0;
+        if compound_reward {
+
+            // Check that trading is not paused as compounding buys FCTR
+            require!(!ctx.accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
+
+            // Check that FCTR is priced by courses as curve reserve can not back minted FCTR
+            require!(
+                ctx.accounts.registrar.price_curve == PriceCurve::Flat,
+                ErrorCode::CompoundWithCurve
+            );
+
+            // Get courses from price feeds or registrar, FCTR feed goes first
+            let mut remaining_accounts = ctx.remaining_accounts;
+            let fctr_course = feed_course(
+                &ctx.accounts.registrar,
+                &ctx.accounts.registrar.fctr_price_feed,
+                ctx.accounts.registrar.fctr_buy_course,
+                remaining_accounts,
+                &ctx.accounts.clock,
+            )?;
+            if ctx.accounts.registrar.fctr_price_feed.is_some() {
+                remaining_accounts = &remaining_accounts[1..];
+            }
+            let bcdev_course = feed_course(
+                &ctx.accounts.registrar,
+                &ctx.accounts.registrar.bcdev_price_feed,
+                ctx.accounts.registrar.bcdev_sell_course,
+                remaining_accounts,
+                &ctx.accounts.clock,
+            )?;
+
+            // Buy FCTR for SOL which BCDEV reward is redeemed for
+            compounded = bcdev_to_fctr(reward, fctr_course, bcdev_course)?;
+            check_supply_caps(
+                &ctx.accounts.registrar,
+                ctx.accounts.mint.supply,
+                ctx.accounts.member.total_bought,
+                compounded,
+            )?;
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info().clone(),
-                token::Transfer {
-                    from: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
-                    to: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
-                    authority: ctx.accounts.member_who_trust_signer.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.registrar_vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
                 },
-                member_signer,
+                registrar_signer,
             );
-            token::transfer(cpi_ctx, amount)?;
-        }        
-        
-        if unstaked {
-            let seeds = &[
-                ctx.accounts.registrar.to_account_info().key.as_ref(),
-                ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
-                &[ctx.accounts.member_to_trust.nonce],
-            ];
-            let member_signer = &[&seeds[..]];              
+            token::mint_to(cpi_ctx, compounded)?;
+
+            // Count compounded FCTR as bought for SOL
+            ctx.accounts.member.total_bought += compounded;
+            ctx.accounts.registrar.sol_supply += compounded;
+        } else {
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info().clone(),
-                token::Transfer {
-                    from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
-                    to: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
-                    authority: ctx.accounts.member_to_trust_signer.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.pool_mint.to_account_info(),
+                    to: ctx.accounts.member_bcdev_vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
                 },
-                member_signer,
-            );                    
-            token::transfer(cpi_ctx, amount)?;         
-            return Ok(());
+                registrar_signer,
+            );
+            token::mint_to(cpi_ctx, reward)?;
         }
-        
-        // Calculate increase of base APR for member who trust his tokens
-        let x_apr = part_calculation_x10_5(
-            &(ctx.accounts.member_who_trust.bought +ctx.accounts.member_who_trust.trusted-ctx.accounts.member_who_trust.shared),
-            &amount,
-            &ACCURACY
+
+        // Close member position in previous round, compounded reward counts as minted BCDEV
+        ctx.accounts.old_round.reward_minted += reward;
+        ctx.accounts.old_round.active_stakers =
+            ctx.accounts.old_round.active_stakers.saturating_sub(1);
+        settle_position(&mut ctx.accounts.member, &ctx.accounts.old_position, false);
+
+        // Principal stays in the stake vault and moves to the current round
+        let token_amount = ctx.accounts.old_position.amount + compounded;
+        ctx.accounts.member.bought -= ctx.accounts.old_position.amount;
+
+        //Calculate time and reward for the current round stake
+        let staked_time = ctx.accounts.round.stop_ts - ctx.accounts.clock.unix_timestamp;
+        let apr = stake_apr(&ctx.accounts.member, &ctx.accounts.round);
+        let spt_amount = apr_calculation (
+            &token_amount,
+            &apr,
+            &ctx.accounts.round.duration,
+            &staked_time,
         );
+        msg!("{} % part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.round.duration as f64, token_amount, spt_amount);
+        let shares = stake_shares(token_amount, staked_time);
 
-        // Save information of the truster in members acount in MemberToMember struct.
-        let mut cur_i =0;
-        let mut found = false;
-        // Find truster in trusted pool
-        for (i, m_t_m) in ctx.accounts.member_to_trust.trusted_pool.iter().enumerate() {
-            
-            if m_t_m.member_who_trust == ctx.accounts.member_who_trust.key() {
-                cur_i = i;
-                found = true;
-                break;
-            }
-        }        
-        
-        if !found {
-            // insert new memtomem
-            let mem_to_mem = MemberToMember {   
-                member_who_trust: *ctx.accounts.member_who_trust.to_account_info().key,  
-                fctr: *ctx.accounts.member_who_trust_fctr_vault.to_account_info().key,
-                bcdev: *ctx.accounts.member_who_trust_bcdev_vault.to_account_info().key,
-                trusted_amount: amount,   
-            };
-            if ctx.accounts.member_to_trust.trusted_pool.len() < 4 {
-                ctx.accounts.member_to_trust.trusted_pool.push(mem_to_mem);
-            } else {msg!(" Member already have 4 shares");}
-        } else {
-            // or encrease shared amount value for member
-            ctx.accounts.member_to_trust.trusted_pool[cur_i].trusted_amount += amount;            
+        // Update round statistics and count member once per round
+        let position = &mut ctx.accounts.position;
+        if position.amount == 0 && position.trusted_amount == 0 {
+            ctx.accounts.round.stakers += 1;
+            ctx.accounts.round.active_stakers += 1;
+            position.member = ctx.accounts.member.key();
+            position.round = ctx.accounts.round.key();
+            position.bump = *ctx.bumps.get("position").unwrap();
         }
-        
-        //Increase trusted counter amount for member to whom trusted 
-        ctx.accounts.member_to_trust.trusted += amount;        
-        
-        // Increase shared amount counter for member who trust
-        ctx.accounts.member_who_trust.shared += amount;
+        ctx.accounts.round.total_staked += token_amount;
+        if ctx.accounts.round.reward_budget.is_none() {
+            ctx.accounts.round.reward_promised += spt_amount;
+        }
+        ctx.accounts.round.total_shares += shares;
 
-        // Save shared time to member
-        ctx.accounts.member_who_trust.shared_time = ctx.accounts.clock.unix_timestamp;
+        // Save stake to the current round position
+        position.amount += token_amount;
+        position.reward += spt_amount;
+        position.shares += shares;
 
-        // Save shared time to truster to member account for calculating 30 rounds lock
-        ctx.accounts.truster_to_member.last_time_trusted=
-            ctx.accounts.clock.unix_timestamp;
-        
-        // If member already staked automatic staking of trusted
-        if ctx.accounts.member_to_trust.staked > 0 {            
-        
-            // Check that member is staking to current round
-            require!(
-                ctx.accounts.registrar.current_round ==
-                    ctx.accounts.round.number,
-                ErrorCode::StakingNotToCurrentRound
-            );
-            
-            // Check that current round still goes on
-            require!(
-                ctx.accounts.round.stop_ts >
-                    ctx.accounts.clock.unix_timestamp,
-                ErrorCode::RoundTimeIsOverStaking
-            );
+        // Save stake to member counters
+        ctx.accounts.member.staked += token_amount;
+        ctx.accounts.member.reward += spt_amount;
+        ctx.accounts.member.shares += shares;
+        ctx.accounts.member.unstaked = false;
+        ctx.accounts.member.staked_round = Some(ctx.accounts.round.key());
 
-            // Get amount for stake
-            let token_amount = amount;            
+        //Emit reward event
+        emit!(RewardEventLog {
+            member: *ctx.accounts.member.to_account_info().key,
+            ts: ctx.accounts.clock.unix_timestamp,
+        });
 
-            // Transfer tokens into the stake vault.
-            {
-                let seeds = &[
-                    ctx.accounts.registrar.to_account_info().key.as_ref(),
-                    ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
-                    &[ctx.accounts.member_to_trust.nonce],
-                ];
-                let member_signer = &[&seeds[..]];
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info().clone(),
-                    token::Transfer {
-                        from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
-                        to: ctx.accounts.registrar_vault.to_account_info(),
-                        authority: ctx.accounts.member_to_trust_signer.to_account_info(),
-                    },
-                    member_signer,
-                );                
-                token::transfer(cpi_ctx, token_amount)?;
-            }
-            
-            // Calculate time for what current stake would be
-            let staked_time = ctx.accounts.round.stop_ts - ctx.accounts.clock.unix_timestamp;
-            msg!("staked_time {}",staked_time);
+        Ok(())
+    }
 
-            // Calculate additional apr if not alredy this member trust
-            let mut add_apr=0;
-            if !found {
-                add_apr = 2 * ACCURACY;
-            }                    
-            msg!("apr {} with add {}",x_apr,add_apr);
+    // Return staked tokens before round end with reward for elapsed time reduced by penalty
+    pub fn early_unstake(ctx: Context<EarlyUnstake>) -> Result<()> {
 
-            // Calculate reward amount for the calculated time with calculated percents
-            let spt_amount = apr_calculation (
-                &token_amount,                
-                &(x_apr + add_apr), 
-                &ctx.accounts.registrar.round_timelock,
-                &staked_time,
+        //Check that round still goes on
+        let now = ctx.accounts.clock.unix_timestamp;
+        require!(
+            ctx.accounts.round.stop_ts > now,
+            ErrorCode::RoundIsOver
+        );
+
+        // Check that position has own amount
+        require!(
+            ctx.accounts.position.amount > 0,
+            ErrorCode::DidntStakedAnything
+        );
+
+        // Trusters are settled only by unstake
+        require!(
+            ctx.accounts.position.trusted_amount == 0,
+            ErrorCode::EarlyUnstakeWithTrusters
+        );
+
+        // Shares of the time left till round end are not earned
+        let token_amount = ctx.accounts.position.amount;
+        let unearned_shares = stake_shares(token_amount, ctx.accounts.round.stop_ts - now);
+        let earned_shares = ctx.accounts.position.shares.saturating_sub(unearned_shares);
+
+        // Get position claim on the budget left or its APR reward
+        let claim = match ctx.accounts.round.reward_budget {
+            Some(budget) => budget_reward(
+                budget - ctx.accounts.round.budget_claimed,
+                ctx.accounts.position.shares,
+                ctx.accounts.round.total_shares,
+            )?,
+            None => ctx.accounts.position.reward,
+        };
+
+        // Get reward for elapsed time from the claim
+        let earned = budget_reward(
+            claim,
+            earned_shares,
+            ctx.accounts.position.shares,
+        )?;
+
+        // Calculate penalty and reward left to member
+        let penalty = (earned as u128 *
+            ctx.accounts.registrar.early_unstake_penalty_bps as u128 /
+            BPS as u128) as u64;
+        let reward = earned - penalty;
+        msg!("early reward {} with penalty {}", reward, penalty);
+
+        // Calculate registrar sign
+        let seeds = &[
+            ctx.accounts.registrar.to_account_info().key.as_ref(),
+            &[ctx.accounts.registrar.nonce],
+        ];
+        let registrar_signer = &[&seeds[..]];
+
+        // Transfer staked tokens back to member
+        {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.registrar_vault.to_account_info(),
+                    to: ctx.accounts.member_fctr_vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
+                },
+                registrar_signer,
             );
-            msg!("spt_amount {}",spt_amount);      
-            msg!("{} percent part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.registrar.round_timelock as f64, token_amount, spt_amount);
-                            
-            // Increase trusted stake value
-            ctx.accounts.member_to_trust.staked_trusted += token_amount;
+            token::transfer(cpi_ctx, token_amount)?;
+        }
 
-            // Null the member legitim amount
-            ctx.accounts.member_to_trust.bought = 0;
-            ctx.accounts.member_to_trust.trusted = 0;
+        // Mint reduced reward to member
+        if reward > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::MintTo {
+                    mint: ctx.accounts.pool_mint.to_account_info(),
+                    to: ctx.accounts.member_bcdev_vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
+                },
+                registrar_signer,
+            );
+            token::mint_to(cpi_ctx, reward)?;
+        }
 
-            // Increase APR reward with calculated amount
-            ctx.accounts.member_to_trust.reward += spt_amount; 
-            // Mark staking status    
-            ctx.accounts.member_to_trust.unstaked = false;       
-        }               
-        
-        // Ctreate trust check struct
-        let check = &mut ctx.accounts.trust_check;
-        check.member_who_trust = ctx.accounts.member_who_trust.key();
-        check.member_to_trust = ctx.accounts.member_to_trust.key();
-        check.amount = amount;
-        check.round = ctx.accounts.round.key();
-        check.time_trusted = ctx.accounts.clock.unix_timestamp;
-        check.burn = false;        
-        
-        Ok(())
-    }
-    
-    // Takes back shared tokens and gives back owns
-    pub fn exit_sharing<'info>( ctx: Context<'_,'_,'_,'info, ExitSharing<'info>>) -> Result<()> {
-        
-        // Find memeber to member struct at shared member trust pool        
-        let mut cur_i = 0;
-        let mut found = false;
-        for (i, m_t_m) in ctx.accounts.member_to_trust.trusted_pool.iter().enumerate() {            
-            if m_t_m.member_who_trust == ctx.accounts.member_who_trust.key() {
-                cur_i = i;
-                found = true;
-                break;
+        // Remove position with its claim from the round
+        let redistribute = ctx.accounts.registrar.penalty_mode == PenaltyMode::Redistribute;
+        let round = &mut ctx.accounts.round;
+        round.total_shares = round.total_shares.saturating_sub(ctx.accounts.position.shares);
+        round.total_staked = round.total_staked.saturating_sub(token_amount);
+        round.reward_minted += reward;
+        round.active_stakers = round.active_stakers.saturating_sub(1);
+        if round.reward_budget.is_some() {
+            // Not paid part of the claim stays in the budget for remaining stakers or is burned
+            round.budget_claimed += if redistribute { reward } else { claim };
+        } else {
+            // Penalty goes to remaining stakers pool and not paid reward is not promised any more
+            let mut forfeited = claim - reward;
+            if redistribute {
+                round.penalty_pool += penalty;
+                forfeited -= penalty;
             }
+            round.reward_promised = round.reward_promised.saturating_sub(forfeited);
         }
 
-        // If not found there is nothing to take back
-        if !found {
-            require!(false, ErrorCode::MemberDidntShare);
-        } 
+        // Settle members counters
+        settle_position(&mut ctx.accounts.member, &ctx.accounts.position, false);
+
+        //Emit early unstake event
+        emit!(EarlyUnstakeEventLog {
+            member: ctx.accounts.member.key(),
+            round: ctx.accounts.round.key(),
+            amount: token_amount,
+            reward,
+            penalty,
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    // Return staked and trusted tokens without reward if staking is stuck
+    pub fn emergency_unstake<'info>( ctx: Context<'_,'_,'_, 'info, EmergencyUnstake<'info>>) -> Result<()> {
+
+        // Check that staking is paused or next round was not started for grace period
+        let registrar = &ctx.accounts.registrar;
+        let stuck =
+            registrar.current_round_account == Some(ctx.accounts.round.key()) &&
+                ctx.accounts.clock.unix_timestamp >
+                    ctx.accounts.round.stop_ts + registrar.emergency_grace_period;
+        require!(
+            registrar.staking_paused || stuck,
+            ErrorCode::EmergencyUnstakeNotAllowed
+        );
+
+        // Check that position has own or trusted amount
+        require!(
+            ctx.accounts.position.amount > 0 || ctx.accounts.position.trusted_amount > 0,
+            ErrorCode::DidntStakedAnything,
+        );
+
+        // Check if trusters are settled by this position
+        let with_trusters = ctx.accounts.position.trusted_amount > 0 &&
+            !ctx.accounts.member.trusted_pool.is_empty();
+
+        // Check that truster vault provided for each truster
+        require!(
+            !with_trusters ||
+                ctx.remaining_accounts.len() == ctx.accounts.member.trusted_pool.len(),
+            ErrorCode::LowRemainingAccountsProvided
+        );
+
+        // Get number of members staked tokens in position
+        let token_amount = ctx.accounts.position.amount;
 
-        // Get shared amount
-        let amount = ctx.accounts.member_to_trust.trusted_pool[cur_i].trusted_amount;
-        
         // Calculate registrar sign
-        let seeds_r = &[
+        let seeds = &[
             ctx.accounts.registrar.to_account_info().key.as_ref(),
             &[ctx.accounts.registrar.nonce],
         ];
-        let registrar_signer = &[&seeds_r[..]];
-        
-        //Check that trusted tokens were already staked
-        if ctx.accounts.member_to_trust.staked_trusted > 0 {
-        
-            // Transfer tokens back to member who trust.
-            {                    
+        let registrar_signer = &[&seeds[..]];
+
+        // Transfer own tokens back to member
+        {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.registrar_vault.to_account_info(),
+                    to: ctx.accounts.member_fctr_vault.to_account_info(),
+                    authority: ctx.accounts.registrar_signer.to_account_info(),
+                },
+                registrar_signer,
+            );
+            token::transfer(cpi_ctx, token_amount)?;
+        }
+
+        // Calculate trusters parts staked in registrar vault
+        let staked_parts = split_staked_trusted(
+            &ctx.accounts.member.trusted_pool,
+            ctx.accounts.position.trusted_amount,
+        );
+
+        // Calculate member sign for not staked trusted tokens
+        let member_seeds = &[
+            ctx.accounts.registrar.to_account_info().key.as_ref(),
+            ctx.accounts.member.to_account_info().key.as_ref(),
+            &[ctx.accounts.member.nonce],
+        ];
+        let member_signer = &[&member_seeds[..]];
+
+        // Transfer trusted tokens back to trusters
+        let trusted_pool = if with_trusters {
+            ctx.accounts.member.trusted_pool.as_slice()
+        } else {
+            &[]
+        };
+        for (i, m_t_m) in trusted_pool.iter().enumerate() {
+
+            // Check that account is equal to saved in Member struct
+            let vault_fctr = &ctx.remaining_accounts[i];
+            require!(vault_fctr.owner == &token::ID, ErrorCode::VaultWrongOwner);
+            require!(vault_fctr.key == &m_t_m.fctr, ErrorCode::VaultWrongKey);
+
+            // Staked part from registrar vault
+            if staked_parts[i] > 0 {
                 let cpi_ctx = CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info().clone(),
                     token::Transfer {
                         from: ctx.accounts.registrar_vault.to_account_info(),
-                        to: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                        to: vault_fctr.to_account_info(),
                         authority: ctx.accounts.registrar_signer.to_account_info(),
                     },
                     registrar_signer,
-                );                   
-                token::transfer(cpi_ctx, amount)?;
+                );
+                token::transfer(cpi_ctx, staked_parts[i])?;
             }
-            
-            // Decrease counter for trusted stake
-            ctx.accounts.member_to_trust.staked_trusted-=amount;
-            
-            //Calculate the decrease of reward for given tokens
-            let staked_time = ctx.accounts.round.stop_ts - ctx.accounts.clock.unix_timestamp;            
-            
-            // The decrease is for 2% multiplied 100000 number for trusted stake    
-            let apr = 2 * ACCURACY;                   
-            
-            //Calculate APR for the calculated time with calculated percents
-            let spt_amount = apr_calculation (
-                &amount,
-                &apr, 
-                &ctx.accounts.registrar.round_timelock,
-                &staked_time,
-            );            
-            msg!("{} percent part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.registrar.round_timelock as f64, amount, spt_amount);  
-        
-            // Decrease APR reward with calculated amount
-            ctx.accounts.member_to_trust.reward -= spt_amount;       
-        
-        
-        } else {
-            // If tokens was not staked, just transfer tokens back.
-            {
-                // Calculate sign for trusted party
-                let seeds = &[
-                    ctx.accounts.registrar.to_account_info().key.as_ref(),
-                    ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
-                    &[ctx.accounts.member_to_trust.nonce],
-                ];
-                let member_signer = &[&seeds[..]];
-                // And transfer tokens 
+
+            // Not staked part from member vault
+            let free_part = m_t_m.trusted_amount - staked_parts[i];
+            if free_part > 0 {
                 let cpi_ctx = CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info().clone(),
                     token::Transfer {
-                        from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
-                        to: ctx.accounts.member_who_trust.to_account_info(),
-                        authority: ctx.accounts.member_to_trust_signer.to_account_info(),
+                        from: ctx.accounts.member_fctr_vault.to_account_info(),
+                        to: vault_fctr.to_account_info(),
+                        authority: ctx.accounts.member_signer.to_account_info(),
                     },
                     member_signer,
                 );
-                token::transfer(cpi_ctx, amount)?;
+                token::transfer(cpi_ctx, free_part)?;
             }
-            
-            // Decrease trusted counter
-            ctx.accounts.member_to_trust.trusted-= amount;
         }
-        
-        
-        // Removes member from trusted pool, and shifts remaining to the left
-        ctx.accounts.member_to_trust.trusted_pool.remove(cur_i);   
-        
-        // Decrease shared counter
-        ctx.accounts.member_who_trust.shared-=amount; 
-  
-        // Burn trust check
-        ctx.accounts.trust_check.burn=true;       
-        
-        // Give trusted amounts to trusters         
-        // Check that member has trusters for tokens and give them back
-        if ctx.accounts.member_who_trust.trusted_pool.len() > 0 {
-        
-            // Check the provided remaining account 
-            let provided_remaining_accounts = &mut ctx.remaining_accounts.iter();
 
-            // Check that number of provide accounts correlates to trusters number
-            require!(
-                provided_remaining_accounts.len()==
-                    ctx.accounts.member_who_trust.trusted_pool.len(),
-                ErrorCode::LowRemainingAccountsProvided
-            );            
-            
-            // Iterate over remaining accounts
-            let mut i=0;
-            while i < ctx.remaining_accounts.len() {
+        // Release member from the round
+        ctx.accounts.round.active_stakers =
+            ctx.accounts.round.active_stakers.saturating_sub(1);
 
-                // Deserealize remaining accounts 
-                let vault_fctr = next_account_info(provided_remaining_accounts)?;
-                                
-                //Check that accounts are spl-tokens
-                require!(vault_fctr.owner== &token::ID, ErrorCode::VaultWrongOwner);
-                
-                // Check that account are equal to saved in Member account
-                require!(vault_fctr.key== &ctx.accounts.member_who_trust.trusted_pool[i].fctr, ErrorCode::VaultWrongKey);
-                
-                // If tokens were already staked
-                if ctx.accounts.member_who_trust.staked_trusted > 0 {
-                
-                    // Transfer fctr back from registrar
-                    {
-                        let cpi_ctx = CpiContext::new_with_signer(
-                            ctx.accounts.token_program.to_account_info().clone(),
-                            token::Transfer {
-                                from: ctx.accounts.registrar_vault.to_account_info(),
-                                to: ctx.remaining_accounts[i].to_account_info(),
-                                authority: ctx.accounts.registrar_signer.to_account_info(),
-                            },
-                            registrar_signer,
-                        );                        
-                        token::transfer(cpi_ctx, ctx.accounts.member_who_trust.trusted_pool[i].trusted_amount)?;
-                    }
-                } else {
-                
-                    // If tokens was not staked transfer fctr back from member
-                    {
-                        // Calculate sign for member
-                        let seeds = &[
-                            ctx.accounts.registrar.to_account_info().key.as_ref(),
-                            ctx.accounts.member_who_trust.to_account_info().key.as_ref(),
-                            &[ctx.accounts.member_who_trust.nonce],
-                        ];
-                        let member_signer = &[&seeds[..]];
-                        // And transfer
-                        let cpi_ctx = CpiContext::new_with_signer(
-                            ctx.accounts.token_program.to_account_info().clone(),
-                            token::Transfer {
-                                from: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
-                                to: ctx.remaining_accounts[i].to_account_info(),
-                                authority: ctx.accounts.member_who_trust_signer.to_account_info(),
-                            },
-                            member_signer,
-                        );
-                        token::transfer(cpi_ctx,  ctx.accounts.member_who_trust.trusted_pool[i].trusted_amount)?;
-                    }
-                }                
-                
-                ctx.accounts.member_who_trust.trusted-= ctx.accounts.member_who_trust.trusted_pool[i].trusted_amount;
-                
-                // Iterate over remaining_accounts
-                i+=1;                
-            }
+        // Forfeit position reward and settle members counters
+        settle_position(&mut ctx.accounts.member, &ctx.accounts.position, with_trusters);
 
-            // Zero trusted pull after token return
-            ctx.accounts.member_who_trust.trusted_pool=vec![];                 
-        }
-        
-        //Emit exit sharing event
-        emit!(ExitSharingEventLog {
-            member: *ctx.accounts.member_who_trust.to_account_info().key,
-            ts: ctx.accounts.clock.unix_timestamp,   
-            //cursor: cursor,
+        //Emit emergency unstake event
+        emit!(EmergencyUnstakeEventLog {
+            member: ctx.accounts.member.key(),
+            amount: token_amount,
+            ts: ctx.accounts.clock.unix_timestamp,
         });
 
         Ok(())
     }
+
+    // Trust some amount to stake member
+    pub fn trust_to_member( ctx: Context<TrustToMember>,amount:u64) -> Result<()> {
     
-    // Check unstaking by the member to who you trust
-    pub fn check_unstaked(ctx: Context<CheckUnstaked>) -> Result<()> {
-        
-        // Check that round is already ended
+        // Check that sharing is not paused
+        require!(!ctx.accounts.registrar.sharing_paused, ErrorCode::ProgramPaused);
+    
+        // Check participating in sharing programm flag 
         require!(
-            ctx.accounts.registrar.current_round >
-                ctx.accounts.round.number,
-            ErrorCode::EarlyUnstakingCheck
+            !ctx.accounts.member_to_trust.dont_participate_sharing,
+            ErrorCode::MemberDontParticipateSharing
         );
 
-        // Check if member not unstaked
-        if !ctx.accounts.member_to_trust.unstaked  {
-
-            // There must be not the current round
-            require!(ctx.accounts.member_to_trust.staked_round !=
-                    Some(ctx.accounts.round.key()),
-                ErrorCode::EarlyUnstakingCheck
-            );            
-        }
-
-        // Decrease member shared counter
-        ctx.accounts.member_who_trust.shared -= ctx.accounts.trust_check.amount;
-
-        // Burn the check
-        ctx.accounts.trust_check.burn=true;        
+        // Check that member shares less then once in 30 times round periods
+        require!(
+            ctx.accounts.clock.unix_timestamp -
+                ctx.accounts.truster_to_member.last_time_trusted >                
+                ctx.accounts.registrar.round_timelock * 30,
+            ErrorCode::OftenSharingParticipation
+        );
         
-        Ok(())
-    }
-    
-    
-    // Check exit by the member to who you trust
-    pub fn check_exit(ctx: Context<CheckExit>) -> Result<()> {
+        // Sharing only bought tokens
+        require!(
+            amount < ctx.accounts.member_who_trust.bought,
+            ErrorCode::ShareOnlyBought
+        );
+        
+        //Check that user has les then four trusters
+        require!(
+            ctx.accounts.member_to_trust.trusted_pool.len() < 4,
+            ErrorCode::MemberTrustersAmountExceeded
+        );
 
-        // Check if shared counter already zeroed
+        // Sharing amount must be less then the half of having
         require!(
-            ctx.accounts.member_to_trust.shared==0,
-            ErrorCode::MemberDidntExit
+            amount < (ctx.accounts.member_who_trust.bought - ctx.accounts.member_who_trust.shared + ctx.accounts.member_who_trust.trusted)/2,
+            ErrorCode::AmountMoreThenHalf
         );
 
-        // Decrease member shared counter
-        ctx.accounts.member_who_trust.shared -= ctx.accounts.trust_check.amount;
+        // Sharing amount must be less quater of bought 
+        require!(
+             (ctx.accounts.member_who_trust.bought -ctx.accounts.member_who_trust.shared + ctx.accounts.member_who_trust.trusted) - amount > ctx.accounts.member_who_trust.bought/4,
+             ErrorCode::AmountLesThenQuater
+        );                
         
-        // Burn the check
-        ctx.accounts.trust_check.burn=true;
+        //Sharing or shared member must be between half and double of self deposit
+        let mut who = 0;
+        let mut to = 0;
         
-        Ok(())
-    }
-    
-    // Withdrow lamports by owner
-    pub fn withdraw_lamports(ctx: Context<WithdrawLamports>) -> Result<()> {
+        // Set amount for staked or unstaked status
+        if ctx.accounts.member_who_trust.bought != 0 {
+            who = ctx.accounts.member_who_trust.bought;
+        }else if ctx.accounts.member_who_trust.staked != 0 {
+            who = ctx.accounts.member_who_trust.staked;
+        }
         
-        // Check if the time after final round is more then two rounds time
-        if 
-            !ctx.accounts.registrar.finalized ||
-            ctx.accounts.clock.unix_timestamp < ctx.accounts.round.stop_ts +
-                ctx.accounts.registrar.round_timelock * 2 {
-
-            // If not,  check that members sold all their tokens
-            require!(
-                ctx.accounts.pool_mint.supply==0 &&
-                ctx.accounts.mint.supply==0,
-                ErrorCode::ThereIsSomeSupply,
+        // Set amount for staked or unstaked status
+        if ctx.accounts.member_to_trust.bought != 0 {
+            to = ctx.accounts.member_to_trust.bought;
+        }else if ctx.accounts.member_to_trust.staked != 0 {
+            to = ctx.accounts.member_to_trust.staked;
+        }        
+        
+        //Sharing or shared member must be between half and double of self deposit 
+        require!(
+            (who >= to/2) &&
+            (who <= to*2),
+            ErrorCode::AmountBetwenHalfAndDouble
+        );
+        
+        //Sharing or shared member must be between half and double of self deposit 
+        require!(
+            (to >= who/2) &&
+            (to <= who*2),
+            ErrorCode::AmountBetwenHalfAndDouble
+        );       
+         
+        // Check if user already unstaked in this round and return tokens if so
+        let unstaked =
+            ctx.accounts.member_to_trust.unstaked &&
+                ctx.accounts.member_to_trust.staked_round ==    
+                Some(ctx.accounts.round.key());        
+       
+        // Transfer trusted tokens to the memeber.
+        {
+            let seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                ctx.accounts.member_who_trust.to_account_info().key.as_ref(),
+                &[ctx.accounts.member_who_trust.nonce],
+            ];
+            let member_signer = &[&seeds[..]];
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                    to: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
+                    authority: ctx.accounts.member_who_trust_signer.to_account_info(),
+                },
+                member_signer,
             );
-        } 
+            token::transfer(cpi_ctx, amount)?;
+        }        
         
-        // Get registrar lamports amount
-        let amount = **ctx.accounts.vault_sol_account.try_borrow_lamports()?;
+        if unstaked {
+            let seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
+                &[ctx.accounts.member_to_trust.nonce],
+            ];
+            let member_signer = &[&seeds[..]];              
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info().clone(),
+                token::Transfer {
+                    from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
+                    to: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                    authority: ctx.accounts.member_to_trust_signer.to_account_info(),
+                },
+                member_signer,
+            );                    
+            token::transfer(cpi_ctx, amount)?;         
+            return Ok(());
+        }
         
-        // Transfer lamports to owner
-        **ctx.accounts.vault_sol_account.try_borrow_mut_lamports()? -= amount ;
-        **ctx.accounts.authority.try_borrow_mut_lamports()? += amount ;          
+        // Calculate increase of base APR for member who trust his tokens
+        let x_apr = part_calculation_x10_5(
+            &(ctx.accounts.member_who_trust.bought +ctx.accounts.member_who_trust.trusted-ctx.accounts.member_who_trust.shared),
+            &amount,
+            &ACCURACY
+        );
+
+        // Save information of the truster in members acount in MemberToMember struct.
+        let mut cur_i =0;
+        let mut found = false;
+        // Find truster in trusted pool
+        for (i, m_t_m) in ctx.accounts.member_to_trust.trusted_pool.iter().enumerate() {
+            
+            if m_t_m.member_who_trust == ctx.accounts.member_who_trust.key() {
+                cur_i = i;
+                found = true;
+                break;
+            }
+        }        
         
-        Ok(())
-    }
-    
+        if !found {
+            // insert new memtomem
+            let mem_to_mem = MemberToMember {   
+                member_who_trust: *ctx.accounts.member_who_trust.to_account_info().key,  
+                fctr: *ctx.accounts.member_who_trust_fctr_vault.to_account_info().key,
+                bcdev: *ctx.accounts.member_who_trust_bcdev_vault.to_account_info().key,
+                trusted_amount: amount,   
+            };
+            if ctx.accounts.member_to_trust.trusted_pool.len() < 4 {
+                ctx.accounts.member_to_trust.trusted_pool.push(mem_to_mem);
+            } else {msg!(" Member already have 4 shares");}
+        } else {
+            // or encrease shared amount value for member
+            ctx.accounts.member_to_trust.trusted_pool[cur_i].trusted_amount += amount;            
+        }
+        
+        //Increase trusted counter amount for member to whom trusted 
+        ctx.accounts.member_to_trust.trusted += amount;        
+        
+        // Increase shared amount counter for member who trust
+        ctx.accounts.member_who_trust.shared += amount;
+
+        // Save shared time to member
+        ctx.accounts.member_who_trust.shared_time = ctx.accounts.clock.unix_timestamp;
+
+        // Save shared time to truster to member account for calculating 30 rounds lock
+        ctx.accounts.truster_to_member.last_time_trusted=
+            ctx.accounts.clock.unix_timestamp;
+        
+        // Load stake position of member to whom trust in the round if any
+        let mut position = if ctx.accounts.position.data_is_empty() {
+            None
+        } else {
+            Some(Account::<StakePosition>::try_from(&ctx.accounts.position)?)
+        };
+        
+        // If member already staked to the round automatic staking of trusted
+        let auto_stake = match &position {
+            Some(position) =>
+                ctx.accounts.member_to_trust.staked_trusted == position.trusted_amount,
+            None => false,
+        };
+        if auto_stake {            
+        
+            // Check that member is staking to current round
+            require!(
+                ctx.accounts.registrar.current_round ==
+                    ctx.accounts.round.number,
+                ErrorCode::StakingNotToCurrentRound
+            );
+            
+            // Check that current round still goes on
+            require!(
+                ctx.accounts.round.stop_ts >
+                    ctx.accounts.clock.unix_timestamp,
+                ErrorCode::RoundTimeIsOverStaking
+            );
+
+            // Get amount for stake
+            let token_amount = amount;            
+
+            // Transfer tokens into the stake vault.
+            {
+                let seeds = &[
+                    ctx.accounts.registrar.to_account_info().key.as_ref(),
+                    ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
+                    &[ctx.accounts.member_to_trust.nonce],
+                ];
+                let member_signer = &[&seeds[..]];
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info().clone(),
+                    token::Transfer {
+                        from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
+                        to: ctx.accounts.registrar_vault.to_account_info(),
+                        authority: ctx.accounts.member_to_trust_signer.to_account_info(),
+                    },
+                    member_signer,
+                );                
+                token::transfer(cpi_ctx, token_amount)?;
+            }
+            
+            // Calculate time for what current stake would be
+            let staked_time = ctx.accounts.round.stop_ts - ctx.accounts.clock.unix_timestamp;
+            msg!("staked_time {}",staked_time);
+
+            // Calculate additional apr if not alredy this member trust
+            let mut add_apr=0;
+            if !found {
+                add_apr = ctx.accounts.round.truster_apr;
+            }                    
+            msg!("apr {} with add {}",x_apr,add_apr);
+
+            // Calculate reward amount for the calculated time with calculated percents
+            let spt_amount = apr_calculation (
+                &token_amount,                
+                &(x_apr + add_apr), 
+                &ctx.accounts.round.duration,
+                &staked_time,
+            );
+            msg!("spt_amount {}",spt_amount);      
+            msg!("{} percent part for {} FCTR is {} BCDEV",staked_time as f64 /ctx.accounts.round.duration as f64, token_amount, spt_amount);
+                            
+            // Increase trusted stake value
+            ctx.accounts.member_to_trust.staked_trusted += token_amount;
+            
+            // Update round statistics
+            ctx.accounts.round.total_trusted_staked += token_amount;
+            if ctx.accounts.round.reward_budget.is_none() {
+                ctx.accounts.round.reward_promised += spt_amount;
+            }
+
+            // Decrease free trusted amount
+            ctx.accounts.member_to_trust.trusted -= token_amount;
+
+            // Increase APR reward with calculated amount
+            ctx.accounts.member_to_trust.reward += spt_amount; 
+            
+            // Add time weighted shares to member and round
+            let shares = stake_shares(token_amount, staked_time);
+            ctx.accounts.member_to_trust.shares += shares;
+            ctx.accounts.round.total_shares += shares;
+            
+            // Save trusted stake to the round position
+            if let Some(position) = position.as_mut() {
+                position.trusted_amount += token_amount;
+                position.reward += spt_amount;
+                position.shares += shares;
+                position.exit(ctx.program_id)?;
+            }
+            // Mark staking status    
+            ctx.accounts.member_to_trust.unstaked = false;       
+        }               
+        
+        // Ctreate trust check struct
+        let check = &mut ctx.accounts.trust_check;
+        check.member_who_trust = ctx.accounts.member_who_trust.key();
+        check.member_to_trust = ctx.accounts.member_to_trust.key();
+        check.amount = amount;
+        check.round = ctx.accounts.round.key();
+        check.time_trusted = ctx.accounts.clock.unix_timestamp;
+        check.burn = false;        
+        check.payer = ctx.accounts.beneficiary.key();
+        
+        // Count open trust check of the round
+        ctx.accounts.round.open_checks += 1;
+        
+        Ok(())
+    }
+    
+    // Takes back shared tokens and gives back owns
+    pub fn exit_sharing<'info>( ctx: Context<'_,'_,'_,'info, ExitSharing<'info>>) -> Result<()> {
+        
+        // Find memeber to member struct at shared member trust pool        
+        let mut cur_i = 0;
+        let mut found = false;
+        for (i, m_t_m) in ctx.accounts.member_to_trust.trusted_pool.iter().enumerate() {            
+            if m_t_m.member_who_trust == ctx.accounts.member_who_trust.key() {
+                cur_i = i;
+                found = true;
+                break;
+            }
+        }
+
+        // If not found there is nothing to take back
+        if !found {
+            require!(false, ErrorCode::MemberDidntShare);
+        } 
+
+        // Get shared amount
+        let amount = ctx.accounts.member_to_trust.trusted_pool[cur_i].trusted_amount;
+        
+        // Calculate registrar sign
+        let seeds_r = &[
+            ctx.accounts.registrar.to_account_info().key.as_ref(),
+            &[ctx.accounts.registrar.nonce],
+        ];
+        let registrar_signer = &[&seeds_r[..]];
+        
+        // Load stake position of member to whom trusted in the staked round if any
+        let mut position = if ctx.accounts.position.data_is_empty() {
+            None
+        } else {
+            Some(Account::<StakePosition>::try_from(&ctx.accounts.position)?)
+        };
+        let position_trusted = match &position {
+            Some(position) => position.trusted_amount,
+            None => 0,
+        };
+        
+        // Check that position holds all staked trusted tokens of the member
+        require!(
+            position_trusted == ctx.accounts.member_to_trust.staked_trusted,
+            ErrorCode::TrustedStakedInOtherRound
+        );
+        
+        // Split taken back tokens to staked in the position and free parts
+        let staked_parts = split_staked_trusted(
+            &ctx.accounts.member_to_trust.trusted_pool,
+            position_trusted,
+        );
+        let staked_amount = staked_parts[cur_i];
+        let free_amount = amount - staked_amount;
+        
+        //Check that trusted tokens were already staked
+        if staked_amount > 0 {
+        
+            // Transfer staked part back to member who trust from registrar.
+            {                    
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info().clone(),
+                    token::Transfer {
+                        from: ctx.accounts.registrar_vault.to_account_info(),
+                        to: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                        authority: ctx.accounts.registrar_signer.to_account_info(),
+                    },
+                    registrar_signer,
+                );                   
+                token::transfer(cpi_ctx, staked_amount)?;
+            }
+            
+            // Load staked round separately only when it is not the check round
+            let mut staked_round = if ctx.accounts.staked_round.key() == ctx.accounts.round.key() {
+                None
+            } else {
+                Some(load_round(&ctx.accounts.staked_round, &ctx.accounts.registrar.key())?)
+            };
+            
+            // Remove taken back tokens from the staked round and position
+            let round: &mut Round = match staked_round.as_mut() {
+                Some(round) => round,
+                None => &mut ctx.accounts.round,
+            };
+            if let Some(position) = position.as_mut() {
+                remove_trusted_stake(
+                    &mut ctx.accounts.member_to_trust,
+                    position,
+                    round,
+                    staked_amount,
+                    ctx.accounts.clock.unix_timestamp,
+                );
+                position.exit(ctx.program_id)?;
+            }
+            if let Some(staked_round) = staked_round {
+                staked_round.exit(ctx.program_id)?;
+            }
+        }
+        
+        // If some tokens were not staked, just transfer them back.
+        if free_amount > 0 {
+            {
+                // Calculate sign for trusted party
+                let seeds = &[
+                    ctx.accounts.registrar.to_account_info().key.as_ref(),
+                    ctx.accounts.member_to_trust.to_account_info().key.as_ref(),
+                    &[ctx.accounts.member_to_trust.nonce],
+                ];
+                let member_signer = &[&seeds[..]];
+                // And transfer tokens 
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info().clone(),
+                    token::Transfer {
+                        from: ctx.accounts.member_to_trust_fctr_vault.to_account_info(),
+                        to: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                        authority: ctx.accounts.member_to_trust_signer.to_account_info(),
+                    },
+                    member_signer,
+                );
+                token::transfer(cpi_ctx, free_amount)?;
+            }
+            
+            // Decrease trusted counter
+            ctx.accounts.member_to_trust.trusted-= free_amount;
+        }
+        
+        
+        // Removes member from trusted pool, and shifts remaining to the left
+        ctx.accounts.member_to_trust.trusted_pool.remove(cur_i);   
+        
+        // Decrease shared counter
+        ctx.accounts.member_who_trust.shared-=amount; 
+  
+        // Burn trust check
+        ctx.accounts.trust_check.burn=true;       
+        ctx.accounts.round.open_checks = ctx.accounts.round.open_checks
+            .checked_sub(1)
+            .ok_or(error!(ErrorCode::MathOverflow))?;
+        
+        // Give trusted amounts to trusters         
+        // Check that member has trusters for tokens and give them back
+        if ctx.accounts.member_who_trust.trusted_pool.len() > 0 {
+        
+            // Check the provided remaining account 
+            let provided_remaining_accounts = &mut ctx.remaining_accounts.iter();
+
+            // Check that number of provide accounts correlates to trusters number
+            require!(
+                provided_remaining_accounts.len()==
+                    ctx.accounts.member_who_trust.trusted_pool.len(),
+                ErrorCode::LowRemainingAccountsProvided
+            );            
+            
+            // Load stake position of member who trust in its staked round if any
+            let mut trust_position = if ctx.accounts.member_who_trust_position.data_is_empty() {
+                None
+            } else {
+                Some(Account::<StakePosition>::try_from(&ctx.accounts.member_who_trust_position)?)
+            };
+            let trust_position_trusted = match &trust_position {
+                Some(position) => position.trusted_amount,
+                None => 0,
+            };
+            
+            // Check that position holds all staked trusted tokens of the member
+            require!(
+                trust_position_trusted == ctx.accounts.member_who_trust.staked_trusted,
+                ErrorCode::TrustedStakedInOtherRound
+            );
+            
+            // Split trusted tokens of every truster to staked and free parts
+            let staked_parts = split_staked_trusted(
+                &ctx.accounts.member_who_trust.trusted_pool,
+                trust_position_trusted,
+            );
+            
+            // Calculate sign for member
+            let seeds = &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                ctx.accounts.member_who_trust.to_account_info().key.as_ref(),
+                &[ctx.accounts.member_who_trust.nonce],
+            ];
+            let member_signer = &[&seeds[..]];
+            
+            // Iterate over remaining accounts
+            let mut free_total = 0;
+            let mut i=0;
+            while i < ctx.remaining_accounts.len() {
+
+                // Deserealize remaining accounts 
+                let vault_fctr = next_account_info(provided_remaining_accounts)?;
+                                
+                //Check that accounts are spl-tokens
+                require!(vault_fctr.owner== &token::ID, ErrorCode::VaultWrongOwner);
+                
+                // Check that account are equal to saved in Member account
+                require!(vault_fctr.key== &ctx.accounts.member_who_trust.trusted_pool[i].fctr, ErrorCode::VaultWrongKey);
+                
+                let staked_amount = staked_parts[i];
+                let free_amount = ctx.accounts.member_who_trust.trusted_pool[i].trusted_amount - staked_amount;
+                
+                // Transfer staked part back from registrar
+                if staked_amount > 0 {
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info().clone(),
+                        token::Transfer {
+                            from: ctx.accounts.registrar_vault.to_account_info(),
+                            to: vault_fctr.to_account_info(),
+                            authority: ctx.accounts.registrar_signer.to_account_info(),
+                        },
+                        registrar_signer,
+                    );                        
+                    token::transfer(cpi_ctx, staked_amount)?;
+                }
+                
+                // Transfer free part back from member
+                if free_amount > 0 {
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info().clone(),
+                        token::Transfer {
+                            from: ctx.accounts.member_who_trust_fctr_vault.to_account_info(),
+                            to: vault_fctr.to_account_info(),
+                            authority: ctx.accounts.member_who_trust_signer.to_account_info(),
+                        },
+                        member_signer,
+                    );
+                    token::transfer(cpi_ctx, free_amount)?;
+                }
+                
+                free_total += free_amount;
+                
+                // Iterate over remaining_accounts
+                i+=1;                
+            }
+            
+            // Decrease trusted counter for free tokens
+            ctx.accounts.member_who_trust.trusted-= free_total;
+            
+            // Remove staked trusted tokens from the staked round and position
+            if trust_position_trusted > 0 {
+                let mut staked_round = if ctx.accounts.member_who_trust_round.key() == ctx.accounts.round.key() {
+                    None
+                } else {
+                    Some(load_round(&ctx.accounts.member_who_trust_round, &ctx.accounts.registrar.key())?)
+                };
+                let round: &mut Round = match staked_round.as_mut() {
+                    Some(round) => round,
+                    None => &mut ctx.accounts.round,
+                };
+                if let Some(position) = trust_position.as_mut() {
+                    remove_trusted_stake(
+                        &mut ctx.accounts.member_who_trust,
+                        position,
+                        round,
+                        trust_position_trusted,
+                        ctx.accounts.clock.unix_timestamp,
+                    );
+                    position.exit(ctx.program_id)?;
+                }
+                if let Some(staked_round) = staked_round {
+                    staked_round.exit(ctx.program_id)?;
+                }
+            }
+
+            // Zero trusted pull after token return
+            ctx.accounts.member_who_trust.trusted_pool=vec![];                 
+        }
+        
+        //Emit exit sharing event
+        emit!(ExitSharingEventLog {
+            member: *ctx.accounts.member_who_trust.to_account_info().key,
+            ts: ctx.accounts.clock.unix_timestamp,   
+            //cursor: cursor,
+        });
+
+        Ok(())
+    }
+    
+    // Check unstaking by the member to who you trust
+    pub fn check_unstaked(ctx: Context<CheckUnstaked>) -> Result<()> {
+        
+        // Check that round is already ended
+        require!(
+            ctx.accounts.registrar.current_round >
+                ctx.accounts.round.number,
+            ErrorCode::EarlyUnstakingCheck
+        );
+
+        // Check if member not unstaked
+        if !ctx.accounts.member_to_trust.unstaked  {
+
+            // There must be not the current round
+            require!(ctx.accounts.member_to_trust.staked_round !=
+                    Some(ctx.accounts.round.key()),
+                ErrorCode::EarlyUnstakingCheck
+            );            
+        }
+
+        // Decrease member shared counter
+        ctx.accounts.member_who_trust.shared -= ctx.accounts.trust_check.amount;
+
+        // Burn the check
+        ctx.accounts.trust_check.burn=true;        
+        ctx.accounts.round.open_checks = ctx.accounts.round.open_checks
+            .checked_sub(1)
+            .ok_or(error!(ErrorCode::MathOverflow))?;
+        
+        Ok(())
+    }
+    
+    
+    // Check exit by the member to who you trust
+    pub fn check_exit(ctx: Context<CheckExit>) -> Result<()> {
+
+        // Check if shared counter already zeroed
+        require!(
+            ctx.accounts.member_to_trust.shared==0,
+            ErrorCode::MemberDidntExit
+        );
+
+        // Decrease member shared counter
+        ctx.accounts.member_who_trust.shared -= ctx.accounts.trust_check.amount;
+        
+        // Burn the check
+        ctx.accounts.trust_check.burn=true;
+        ctx.accounts.round.open_checks = ctx.accounts.round.open_checks
+            .checked_sub(1)
+            .ok_or(error!(ErrorCode::MathOverflow))?;
+        
+        Ok(())
+    }
+    
+    // Close old round account and return rent to payer
+    pub fn close_round(ctx: Context<CloseRound>) -> Result<()> {
+
+        // Check that round is older then the current one
+        require!(
+            ctx.accounts.registrar.current_round >
+                ctx.accounts.round.number,
+            ErrorCode::RoundIsInUse
+        );
+
+        // Check that nobody is staked to round and all trust checks are burned
+        require!(
+            ctx.accounts.round.active_stakers == 0 &&
+                ctx.accounts.round.open_checks == 0,
+            ErrorCode::RoundIsInUse
+        );
+
+        Ok(())
+    }
+
+    // Close burned trust check account and return rent to payer
+    pub fn close_trust_check(_ctx: Context<CloseTrustCheck>) -> Result<()> {
+        Ok(())
+    }
+    
+    // Withdrow lamports by owner
+    pub fn withdraw_lamports(ctx: Context<WithdrawLamports>) -> Result<()> {
+        
+        // Check if the time after final round is more then two rounds time
+        if 
+            !ctx.accounts.registrar.finalized ||
+            ctx.accounts.clock.unix_timestamp < ctx.accounts.round.stop_ts +
+                ctx.accounts.registrar.round_timelock * 2 {
+
+            // If not,  check that members sold all their tokens
+            require!(
+                ctx.accounts.pool_mint.supply==0 &&
+                ctx.accounts.mint.supply==0,
+                ErrorCode::ThereIsSomeSupply,
+            );
+        } 
+        
+        // Get registrar lamports amount
+        let amount = **ctx.accounts.vault_sol_account.try_borrow_lamports()?;
+        
+        // Transfer lamports to owner
+        **ctx.accounts.vault_sol_account.try_borrow_mut_lamports()? -= amount ;
+        **ctx.accounts.authority.try_borrow_mut_lamports()? += amount ;          
+        
+        Ok(())
+    }
+    
+    // Create M-of-N admin multisig to be used as registrar authority
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        owners: Vec<Pubkey>,
+        threshold: u64,
+        nonce: u8,
+    ) -> Result<()> {
+
+        // Check that threshold is reachable by owners
+        require!(
+            threshold > 0 && threshold <= owners.len() as u64,
+            ErrorCode::InvalidThreshold
+        );
+
+        // Check that owners are unique
+        for (i, owner) in owners.iter().enumerate() {
+            require!(!owners[..i].contains(owner), ErrorCode::InvalidThreshold);
+        }
+
+        // Check that nonce leads to multisig signer
+        Pubkey::create_program_address(
+            &[ctx.accounts.multisig.key().as_ref(), &[nonce]],
+            ctx.program_id,
+        )
+        .map_err(|_| error!(ErrorCode::InvalidNonce))?;
+
+        // Create multisig struct
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.nonce = nonce;
+
+        Ok(())
+    }
+
+    // Propose instruction to be signed by multisig
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_program_id: Pubkey,
+        proposal_accounts: Vec<ProposalAccount>,
+        proposal_data: Vec<u8>,
+    ) -> Result<()> {
+
+        // Find proposer in multisig owners
+        let owner_index = ctx.accounts.multisig.owners
+            .iter()
+            .position(|owner| owner == ctx.accounts.proposer.key)
+            .ok_or(ErrorCode::NotMultisigOwner)?;
+
+        // Create proposal struct approved by proposer
+        let mut signers = vec![false; ctx.accounts.multisig.owners.len()];
+        signers[owner_index] = true;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.multisig = ctx.accounts.multisig.key();
+        proposal.program_id = proposal_program_id;
+        proposal.accounts = proposal_accounts;
+        proposal.data = proposal_data;
+        proposal.signers = signers;
+        proposal.did_execute = false;
+
+        Ok(())
+    }
+
+    // Approve proposal by multisig owner
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+
+        // Find owner in multisig owners
+        let owner_index = ctx.accounts.multisig.owners
+            .iter()
+            .position(|owner| owner == ctx.accounts.owner.key)
+            .ok_or(ErrorCode::NotMultisigOwner)?;
+
+        // Mark approval
+        ctx.accounts.proposal.signers[owner_index] = true;
+
+        Ok(())
+    }
+
+    // Execute approved proposal signed by multisig signer
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+
+        // Check that proposal is not executed yet
+        require!(!ctx.accounts.proposal.did_execute, ErrorCode::ProposalAlreadyExecuted);
+
+        // Check that proposal has enouph approvals
+        let approvals = ctx.accounts.proposal.signers
+            .iter()
+            .filter(|signed| **signed)
+            .count() as u64;
+        require!(
+            approvals >= ctx.accounts.multisig.threshold,
+            ErrorCode::NotEnoughApprovals
+        );
+
+        // Build instruction with multisig signer as signer
+        let multisig_signer = ctx.accounts.multisig_signer.key();
+        let ix = Instruction {
+            program_id: ctx.accounts.proposal.program_id,
+            accounts: ctx.accounts.proposal.accounts
+                .iter()
+                .map(|acc| AccountMeta {
+                    pubkey: acc.pubkey,
+                    is_signer: acc.is_signer || acc.pubkey == multisig_signer,
+                    is_writable: acc.is_writable,
+                })
+                .collect(),
+            data: ctx.accounts.proposal.data.clone(),
+        };
+
+        // Mark proposal executed before invoke
+        ctx.accounts.proposal.did_execute = true;
+        ctx.accounts.proposal.exit(ctx.program_id)?;
+
+        // Invoke instruction with multisig sign
+        let multisig_key = ctx.accounts.multisig.key();
+        let seeds = &[
+            multisig_key.as_ref(),
+            &[ctx.accounts.multisig.nonce],
+        ];
+        let signer = &[&seeds[..]];
+        invoke_signed(&ix, ctx.remaining_accounts, signer)?;
+
+        Ok(())
+    }
+    
+}
+
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(zero)]
+    multisig: Box<Account<'info, AdminMultisig>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    multisig: Box<Account<'info, AdminMultisig>>,
+    #[account(zero)]
+    proposal: Box<Account<'info, AdminProposal>>,
+    proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    multisig: Box<Account<'info, AdminMultisig>>,
+    #[account(
+        mut,
+        has_one = multisig,
+    )]
+    proposal: Box<Account<'info, AdminProposal>>,
+    owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    multisig: Box<Account<'info, AdminMultisig>>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [multisig.key().as_ref()],
+        bump = multisig.nonce,
+    )]
+    multisig_signer: AccountInfo<'info>,
+    #[account(
+        mut,
+        has_one = multisig,
+    )]
+    proposal: Box<Account<'info, AdminProposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(_bump: u8)]
+pub struct Initialize<'info> {
+    #[account(zero)]
+    registrar: Account<'info, Registrar>,    
+    pool_mint: Account<'info, Mint>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(
+        init,
+        seeds = [
+            b"sol-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        payer = payer,
+        bump,
+        space = 8 + 8,
+    )]
+    vault_sol_account: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+    
+}
+
+impl<'info> Initialize<'info> {
+    fn accounts(ctx: &Context<Initialize<'info>>, nonce: u8) -> Result<()> {
+        let registrar_signer = Pubkey::create_program_address(
+            &[
+                ctx.accounts.registrar.to_account_info().key.as_ref(),
+                &[nonce],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| error!(ErrorCode::InvalidNonce))?;
+        if ctx.accounts.pool_mint.mint_authority != COption::Some(registrar_signer) {
+            return err!(ErrorCode::InvalidPoolMintAuthority);
+        }
+        assert!(ctx.accounts.pool_mint.supply == 0);
+        Ok(())
+    }
+}
+
+
+#[derive(Accounts)]
+pub struct SetPrices<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct SetFee<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSupplyCaps<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMerkleRoot<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferralBps<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreatePriceFeed<'info> {
+    #[account(zero)]
+    price_feed: Account<'info, PriceFeed>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    #[account(mut, has_one = authority)]
+    price_feed: Account<'info, PriceFeed>,
+    authority: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct SetPriceFeeds<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMember<'info> {    
+    registrar: Box<Account<'info, Registrar>>,          
+    #[account(
+        init,
+        payer = beneficiary,
+        space = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + (1+32) + 1 + 8  + 1 + 1 + 4 + (32 + 32 + 32 + 8 )*4 + 8 + (1+32) + 4 + 8 + 16,
+        
+    )]
+    member: Box<Account<'info, Member>>,
+    #[account(mut)]
+    beneficiary: Signer<'info>,   
+    /// CHECK: It checked in CreateMember::accounts 
+    member_signer: AccountInfo<'info>,    
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,    
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,
+}
+
+impl<'info> CreateMember<'info> {
+    fn accounts(ctx: &Context<CreateMember>, nonce: u8) -> Result<()> {
+        let seeds = &[
+            ctx.accounts.registrar.to_account_info().key.as_ref(),
+            ctx.accounts.member.to_account_info().key.as_ref(),
+            &[nonce],
+        ];
+        let member_signer = Pubkey::create_program_address(seeds, ctx.program_id)
+            .map_err(|_| error!(ErrorCode::InvalidNonce))?;
+        if &member_signer != ctx.accounts.member_signer.to_account_info().key {
+            return err!(ErrorCode::InvalidMemberSigner);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct BuyFctr<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        has_one = pool_mint,
+    )]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with registrar
+    #[account(
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
+    )]
+    registrar_signer: AccountInfo<'info>,
+    #[account(mut)]
+    mint: Account<'info, Mint>,
+    #[account(mut)]
+    pool_mint: Account<'info, Mint>,
+    #[account(mut)]
+    buyer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = registrar,
+        constraint = member.beneficiary == *buyer.key
+    )]
+    member: Box<Account<'info, Member>>,
+    /// CHECK: checked with seed
+    #[account(
+        mut,
+        seeds = [
+            b"sol-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        bump = registrar.sol_vault_bump,
+    )]
+    vault_sol_account: AccountInfo<'info>,  
+    /// CHECK: checked with registrar
+    #[account(mut, address = registrar.treasury)]
+    treasury: AccountInfo<'info>,
+    #[account(mut)]
+    vault: Account<'info, TokenAccount>,
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+pub struct TransferFctr<'info> {
+    //#[account(has_one = pool_mint)]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with seeds
+    #[account(
+        seeds = [
+            registrar.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump = member.nonce,
+    )]
+    member_signer: AccountInfo<'info>,  
+    beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        has_one = registrar,
+        has_one = beneficiary,
+    )]
+    member: Box<Account<'info, Member>>,
+    #[account(mut)]
+    vault_fctr: Account<'info, TokenAccount>,
+    #[account(mut)]
+    token_holder:Account<'info, TokenAccount>,
+    token_program: Program<'info, Token>,    
+}
+
+#[derive(Accounts)]
+pub struct SellFctr<'info> {
+    #[account(mut, has_one = mint)]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [
+            registrar.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump = member.nonce,
+    )]
+    member_signer: AccountInfo<'info>,
+    #[account(mut)]
+    mint: Account<'info, Mint>,
+    #[account(mut)]
+    beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        has_one = registrar,
+        has_one = beneficiary,
+    )]
+    member: Box<Account<'info, Member>>,
+    /// CHECK: check with seed
+    #[account(
+        mut,
+        seeds = [
+            b"sol-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        bump = registrar.sol_vault_bump,
+    )]
+    vault_sol_account: AccountInfo<'info>,  
+    /// CHECK: checked with registrar
+    #[account(mut, address = registrar.treasury)]
+    treasury: AccountInfo<'info>,
+    #[account(mut)]
+    vault: Account<'info, TokenAccount>,
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
+    system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetQuoteMint<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
+    )]
+    registrar_signer: AccountInfo<'info>,
+    quote_mint: Account<'info, Mint>,
+    #[account(
+        constraint = quote_vault.mint == quote_mint.key(),
+        constraint = quote_vault.owner == registrar_signer.key(),
+    )]
+    quote_vault: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct BuyFctrWithQuote<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        constraint = registrar.quote_vault == Some(quote_vault.key()) @ ErrorCode::QuoteNotConfigured,
+    )]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
+    )]
+    registrar_signer: AccountInfo<'info>,
+    #[account(mut)]
+    mint: Account<'info, Mint>,
+    buyer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = registrar,
+        constraint = member.beneficiary == *buyer.key
+    )]
+    member: Box<Account<'info, Member>>,
+    #[account(mut)]
+    buyer_quote_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    quote_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    vault: Account<'info, TokenAccount>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SellFctrForQuote<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        constraint = registrar.quote_vault == Some(quote_vault.key()) @ ErrorCode::QuoteNotConfigured,
+    )]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
+    )]
+    registrar_signer: AccountInfo<'info>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [
+            registrar.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump = member.nonce,
+    )]
+    member_signer: AccountInfo<'info>,
+    #[account(mut)]
+    mint: Account<'info, Mint>,
+    beneficiary: Signer<'info>,
+    #[account(
+        mut,
+        has_one = registrar,
+        has_one = beneficiary,
+    )]
+    member: Box<Account<'info, Member>>,
+    #[account(mut)]
+    beneficiary_quote_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    quote_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    vault: Account<'info, TokenAccount>,
+    token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(_bump: u8)]
-pub struct Initialize<'info> {
-    #[account(zero)]
-    registrar: Account<'info, Registrar>,    
+pub struct SellBcdev<'info> {
+    #[account(has_one = pool_mint)]
+    registrar: Account<'info, Registrar>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [
+            registrar.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump = member.nonce,
+    )]
+    member_signer: AccountInfo<'info>,
+    #[account(mut)]
     pool_mint: Account<'info, Mint>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    beneficiary: Signer<'info>,
     #[account(
-        init,
-        seeds = [b"sol-seed".as_ref()],        
-        payer = payer,
-        bump,
-        space = 8 + 8,
+        mut,
+        has_one = registrar,
+        has_one = beneficiary,
     )]
-    vault_sol_account: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
+    member: Box<Account<'info, Member>>,
+    /// CHECK: check with seed
+    #[account(
+        mut,
+        seeds = [
+            b"sol-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        bump = registrar.sol_vault_bump,
+    )]
+    vault_sol_account: AccountInfo<'info>,  
+    /// CHECK: checked with registrar
+    #[account(mut, address = registrar.treasury)]
+    treasury: AccountInfo<'info>,
     #[account(mut)]
-    payer: Signer<'info>,
+    vault_bcdev: Account<'info, TokenAccount>,
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
     system_program: Program<'info, System>,
-    
 }
 
-impl<'info> Initialize<'info> {
-    fn accounts(ctx: &Context<Initialize<'info>>, nonce: u8) -> Result<()> {
-        let registrar_signer = Pubkey::create_program_address(
-            &[
-                ctx.accounts.registrar.to_account_info().key.as_ref(),
-                &[nonce],
-            ],
-            ctx.program_id,
-        )
-        .map_err(|_| error!(ErrorCode::InvalidNonce))?;
-        if ctx.accounts.pool_mint.mint_authority != COption::Some(registrar_signer) {
-            return err!(ErrorCode::InvalidPoolMintAuthority);
-        }
-        assert!(ctx.accounts.pool_mint.supply == 0);
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct StartRound<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+    #[account(zero)]
+    round: Box<Account<'info, Round>>,    
+    clock: Sysvar<'info, Clock>,
+    system_program: Program<'info, System>,    
 }
 
-
 #[derive(Accounts)]
-pub struct CreateMember<'info> {    
-    registrar: Box<Account<'info, Registrar>>,          
+pub struct CrankRound<'info> {
+    #[account(mut)]
+    registrar: Account<'info, Registrar>,
+    #[account(
+        has_one = registrar,
+        constraint = registrar.current_round_account == Some(current_round.key()),
+    )]
+    current_round: Box<Account<'info, Round>>,
     #[account(
         init,
-        payer = beneficiary,
-        space = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + (1+32) + 1 + 8  + 1 + 1 + 4 + (32 + 32 + 32 + 8 )*4,
-        
+        payer = cranker,
+        space = 8 + 32 + 8 + 8 + 4 + 1 + 8 + 8 + 8 + (1 + 8) + 16 + 8 + 8 + 4 + 8 + 8 + 4 + 4 + 32 + 8 + 8,
+        seeds = [
+            b"round".as_ref(),
+            registrar.key().as_ref(),
+            &(registrar.current_round + 1).to_le_bytes(),
+        ],
+        bump,
     )]
-    member: Box<Account<'info, Member>>,
+    round: Box<Account<'info, Round>>,
     #[account(mut)]
-    beneficiary: Signer<'info>,   
-    /// CHECK: It checked in CreateMember::accounts 
-    member_signer: AccountInfo<'info>,    
-    token_program: Program<'info, Token>,
-    rent: Sysvar<'info, Rent>,    
+    cranker: Signer<'info>,
+    /// CHECK: checked with seed, funded by anyone for crank tips
+    #[account(
+        mut,
+        seeds = [
+            b"crank-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        bump,
+    )]
+    crank_tip_vault: AccountInfo<'info>,
+    clock: Sysvar<'info, Clock>,
+    rent: Sysvar<'info, Rent>,
     system_program: Program<'info, System>,
 }
 
-impl<'info> CreateMember<'info> {
-    fn accounts(ctx: &Context<CreateMember>, nonce: u8) -> Result<()> {
-        let seeds = &[
-            ctx.accounts.registrar.to_account_info().key.as_ref(),
-            ctx.accounts.member.to_account_info().key.as_ref(),
-            &[nonce],
-        ];
-        let member_signer = Pubkey::create_program_address(seeds, ctx.program_id)
-            .map_err(|_| error!(ErrorCode::InvalidNonce))?;
-        if &member_signer != ctx.accounts.member_signer.to_account_info().key {
-            return err!(ErrorCode::InvalidMemberSigner);
-        }
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct SetCrankTip<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct BuyFctr<'info> {
-    #[account(has_one = mint)]
+pub struct ProposeAuthority<'info> {
+    #[account(mut, has_one = authority)]
     registrar: Account<'info, Registrar>,
-    /// CHECK: checked with registrar
+    authority: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        constraint = registrar.pending_authority == Some(new_authority.key()) @ ErrorCode::NotPendingAuthority,
+    )]
+    registrar: Account<'info, Registrar>,
+    new_authority: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct Restake<'info> {
+    #[account(
+        mut,
+        has_one = mint,
+        has_one = pool_mint,
+    )]
+    registrar: Box<Account<'info, Registrar>>,
+    /// CHECK: checked with seed
     #[account(
         seeds = [registrar.to_account_info().key.as_ref()],
         bump = registrar.nonce,
     )]
     registrar_signer: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = registrar_vault.mint == registrar.mint,
+        constraint = registrar_vault.owner == registrar_signer.key(),
+    )]
+    registrar_vault: Account<'info, TokenAccount>,
     #[account(mut)]
     mint: Account<'info, Mint>,
     #[account(mut)]
-    buyer: Signer<'info>,
+    pool_mint: Account<'info, Mint>,
+    ///Staker
+    #[account(mut)]
+    beneficiary: Signer<'info>,
     #[account(
         mut,
         has_one = registrar,
-        constraint = member.beneficiary == *buyer.key
+        has_one = beneficiary,
     )]
     member: Box<Account<'info, Member>>,
-    /// CHECK: checked with seed
+    #[account(mut)]
+    member_bcdev_vault: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"sol-seed",],
-        bump
+        has_one = registrar,
     )]
-    vault_sol_account: AccountInfo<'info>,  
-    #[account(mut)]
-    vault: Account<'info, TokenAccount>,
+    old_round: Box<Account<'info, Round>>,
+    #[account(
+        mut,
+        close = beneficiary,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            old_round.key().as_ref(),
+        ],
+        bump = old_position.bump,
+    )]
+    old_position: Box<Account<'info, StakePosition>>,
+    #[account(
+        mut,
+        has_one = registrar,
+        constraint = registrar.current_round_account == Some(round.key()),
+    )]
+    round: Box<Account<'info, Round>>,
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 16 + 1,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump,
+    )]
+    position: Box<Account<'info, StakePosition>>,
+    clock: Sysvar<'info, Clock>,
     token_program: Program<'info, Token>,
     system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetEmergencyGracePeriod<'info> {
+    #[account(mut, has_one = authority)]
+    registrar: Account<'info, Registrar>,
+    authority: Signer<'info>,
+}
 
 #[derive(Accounts)]
-pub struct TransferFctr<'info> {
-    //#[account(has_one = pool_mint)]
+pub struct SetEarlyUnstakePenalty<'info> {
+    #[account(mut, has_one = authority)]
     registrar: Account<'info, Registrar>,
-    /// CHECK: checked with seeds
+    authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EarlyUnstake<'info> {
     #[account(
-        seeds = [
-            registrar.to_account_info().key.as_ref(),
-            member.to_account_info().key.as_ref(),
-        ],
-        bump = member.nonce,
+        has_one = pool_mint,
     )]
-    member_signer: AccountInfo<'info>,  
+    registrar: Box<Account<'info, Registrar>>,
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
+    )]
+    registrar_signer: AccountInfo<'info>,
+    #[account(mut)]
+    registrar_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pool_mint: Account<'info, Mint>,
+    ///Staker
+    #[account(mut)]
     beneficiary: Signer<'info>,
     #[account(
         mut,
@@ -1234,27 +3316,43 @@ pub struct TransferFctr<'info> {
     )]
     member: Box<Account<'info, Member>>,
     #[account(mut)]
-    vault_fctr: Account<'info, TokenAccount>,
+    member_fctr_vault: Account<'info, TokenAccount>,
     #[account(mut)]
-    token_holder:Account<'info, TokenAccount>,
-    token_program: Program<'info, Token>,    
+    member_bcdev_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = registrar,
+        constraint = registrar.current_round_account == Some(round.key()),
+    )]
+    round: Box<Account<'info, Round>>,
+    #[account(
+        mut,
+        close = beneficiary,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump = position.bump,
+        has_one = round,
+    )]
+    position: Box<Account<'info, StakePosition>>,
+    clock: Sysvar<'info, Clock>,
+    token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct SellFctr<'info> {
-    #[account(has_one = mint)]
-    registrar: Account<'info, Registrar>,
+pub struct EmergencyUnstake<'info> {
+    registrar: Box<Account<'info, Registrar>>,
     /// CHECK: checked with seed
     #[account(
-        seeds = [
-            registrar.to_account_info().key.as_ref(),
-            member.to_account_info().key.as_ref(),
-        ],
-        bump = member.nonce,
+        seeds = [registrar.to_account_info().key.as_ref()],
+        bump = registrar.nonce,
     )]
-    member_signer: AccountInfo<'info>,
+    registrar_signer: AccountInfo<'info>,
     #[account(mut)]
-    mint: Account<'info, Mint>,
+    registrar_vault: Account<'info, TokenAccount>,
+    ///Staker
     #[account(mut)]
     beneficiary: Signer<'info>,
     #[account(
@@ -1263,24 +3361,6 @@ pub struct SellFctr<'info> {
         has_one = beneficiary,
     )]
     member: Box<Account<'info, Member>>,
-    /// CHECK: check with seed
-    #[account(
-        mut,
-        seeds = [b"sol-seed",],
-        bump
-    )]
-    vault_sol_account: AccountInfo<'info>,  
-    #[account(mut)]
-    vault: Account<'info, TokenAccount>,
-    token_program: Program<'info, Token>,
-    system_program: Program<'info, System>,
-}
-
-
-#[derive(Accounts)]
-pub struct SellBcdev<'info> {
-    #[account(has_one = pool_mint)]
-    registrar: Account<'info, Registrar>,
     /// CHECK: checked with seed
     #[account(
         seeds = [
@@ -1291,37 +3371,34 @@ pub struct SellBcdev<'info> {
     )]
     member_signer: AccountInfo<'info>,
     #[account(mut)]
-    pool_mint: Account<'info, Mint>,
-    #[account(mut)]
-    beneficiary: Signer<'info>,
+    member_fctr_vault: Account<'info, TokenAccount>,
     #[account(
         mut,
         has_one = registrar,
-        has_one = beneficiary,
     )]
-    member: Box<Account<'info, Member>>,
-    /// CHECK: check with seed
+    round: Box<Account<'info, Round>>,
     #[account(
         mut,
-        seeds = [b"sol-seed",],
-        bump
+        close = beneficiary,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump = position.bump,
+        has_one = round,
     )]
-    vault_sol_account: AccountInfo<'info>,  
-    #[account(mut)]
-    vault_bcdev: Account<'info, TokenAccount>,
+    position: Box<Account<'info, StakePosition>>,
+    clock: Sysvar<'info, Clock>,
     token_program: Program<'info, Token>,
-    system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct StartRound<'info> {
+pub struct SetPause<'info> {
     #[account(mut, has_one = authority)]
     registrar: Account<'info, Registrar>,
     authority: Signer<'info>,
-    #[account(zero)]
-    round: Box<Account<'info, Round>>,    
     clock: Sysvar<'info, Clock>,
-    system_program: Program<'info, System>,    
 }
 
 #[derive(Accounts)]
@@ -1335,6 +3412,7 @@ pub struct Stake<'info> {
     registrar_vault: Account<'info, TokenAccount>,
     #[account(mut)]
     pool_mint: Account<'info, Mint>,        
+    #[account(mut)]
     beneficiary: Signer<'info>,
     #[account(
         mut,
@@ -1354,10 +3432,23 @@ pub struct Stake<'info> {
     #[account(mut)]
     member_fctr_vault: Account<'info, TokenAccount>,    
     #[account(       
+        mut,
         has_one = registrar, 
 	constraint = registrar.current_round_account == Some(round.key()),         
     )]
     round: Box<Account<'info, Round>>,    
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 16 + 1,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump,
+    )]
+    position: Box<Account<'info, StakePosition>>,
     clock: Sysvar<'info, Clock>,
     token_program: Program<'info, Token>,
     system_program: Program<'info, System>,    
@@ -1381,6 +3472,7 @@ pub struct Unstake<'info> {
     #[account(mut)]
     pool_mint: Account<'info, Mint>,
     ///Staker
+    #[account(mut)]
     beneficiary: Signer<'info>,
     #[account(
         mut,
@@ -1388,14 +3480,36 @@ pub struct Unstake<'info> {
         has_one = beneficiary,
     )]
     member: Box<Account<'info, Member>>,    
+    /// CHECK: checked with seed
+    #[account(
+        seeds = [
+            registrar.to_account_info().key.as_ref(),
+            member.to_account_info().key.as_ref(),
+        ],
+        bump = member.nonce,
+    )]
+    member_signer: AccountInfo<'info>,
     #[account(mut)]
     member_fctr_vault: Account<'info, TokenAccount>,
     #[account(mut)]
     member_bcdev_vault: Account<'info, TokenAccount>,
     #[account(       
+        mut,
         has_one = registrar,     
     )]
     round: Box<Account<'info, Round>>,    
+    #[account(
+        mut,
+        close = beneficiary,
+        seeds = [
+            b"position".as_ref(),
+            member.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump = position.bump,
+        has_one = round,
+    )]
+    position: Box<Account<'info, StakePosition>>,
     clock: Sysvar<'info, Clock>,
     token_program: Program<'info, Token>,
     system_program: Program<'info, System>,    
@@ -1454,10 +3568,22 @@ pub struct TrustToMember<'info> {
     #[account(mut)]
     registrar_vault: Account<'info, TokenAccount>,
     #[account(       
+        mut,
         has_one = registrar, 
 	constraint = registrar.current_round_account == Some(round.key()),         
     )]
     round: Box<Account<'info, Round>>,        
+    /// CHECK: checked with seed and loaded when exists
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            member_to_trust.key().as_ref(),
+            round.key().as_ref(),
+        ],
+        bump,
+    )]
+    position: AccountInfo<'info>,
     #[account(
         init_if_needed,
         payer = beneficiary,
@@ -1505,6 +3631,20 @@ pub struct ExitSharing<'info> {
     member_who_trust_signer: AccountInfo<'info>,
     #[account(mut)]
     member_who_trust_fctr_vault: Account<'info, TokenAccount>,
+    /// CHECK: round where member who trust staked its trusted tokens, loaded in handler
+    #[account(mut)]
+    member_who_trust_round: AccountInfo<'info>,
+    /// CHECK: checked with seed and loaded when exists
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            member_who_trust.key().as_ref(),
+            member_who_trust_round.key().as_ref(),
+        ],
+        bump,
+    )]
+    member_who_trust_position: AccountInfo<'info>,
     
     /// Member to whom trust
     //account_to_trust: AccountInfo<'info>,
@@ -1527,9 +3667,24 @@ pub struct ExitSharing<'info> {
     
     ///Round
     #[account(       
+        mut,
         has_one = registrar,                 
     )]
     round: Box<Account<'info, Round>>,  
+    /// CHECK: round where member to trust staked trusted tokens, loaded in handler
+    #[account(mut)]
+    staked_round: AccountInfo<'info>,
+    /// CHECK: checked with seed and loaded when exists
+    #[account(
+        mut,
+        seeds = [
+            b"position".as_ref(),
+            member_to_trust.key().as_ref(),
+            staked_round.key().as_ref(),
+        ],
+        bump,
+    )]
+    position: AccountInfo<'info>,
     #[account(  
         mut,
         has_one = round,   
@@ -1567,8 +3722,11 @@ pub struct WithdrawLamports<'info> {
     /// CHECK: checked with seed
     #[account(
         mut,
-        seeds = [b"sol-seed",],
-        bump
+        seeds = [
+            b"sol-seed".as_ref(),
+            registrar.to_account_info().key.as_ref(),
+        ],
+        bump = registrar.sol_vault_bump,
     )]
     vault_sol_account: AccountInfo<'info>,  
     
@@ -1581,6 +3739,7 @@ pub struct WithdrawLamports<'info> {
 pub struct CheckUnstaked<'info> {    
     registrar: Account<'info, Registrar>,    
     #[account(       
+        mut,
         has_one = registrar,                 
     )]
     round: Box<Account<'info, Round>>,  
@@ -1611,6 +3770,7 @@ pub struct CheckUnstaked<'info> {
 pub struct CheckExit<'info> {    
     registrar: Account<'info, Registrar>,    
     #[account(       
+        mut,
         has_one = registrar,                 
     )]
     round: Box<Account<'info, Round>>,  
@@ -1637,6 +3797,36 @@ pub struct CheckExit<'info> {
 }
 
 
+#[derive(Accounts)]
+pub struct CloseRound<'info> {
+    registrar: Account<'info, Registrar>,
+    #[account(
+        mut,
+        has_one = registrar,
+        has_one = payer,
+        close = payer,
+    )]
+    round: Box<Account<'info, Round>>,
+    /// CHECK: checked with round payer
+    #[account(mut)]
+    payer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseTrustCheck<'info> {
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        constraint = trust_check.burn @ ErrorCode::TrustCheckNotBurned,
+    )]
+    trust_check: Box<Account<'info, TrustCheck>>,
+    /// CHECK: checked with trust check payer
+    #[account(mut)]
+    payer: AccountInfo<'info>,
+}
+
+
 #[account]
 pub struct Registrar {
     /// Priviledged account.
@@ -1655,6 +3845,76 @@ pub struct Registrar {
     pub current_round: u32,
     // Last round account
     pub current_round_account: Option<Pubkey>,    
+    /// FCTR amount sold for one SOL
+    pub fctr_buy_course: u64,
+    /// FCTR amount bought back for one SOL
+    pub fctr_sell_course: u64,
+    /// BCDEV amount bought back for one SOL
+    pub bcdev_sell_course: u64,
+    /// Price feed used instead of FCTR buy course
+    pub fctr_price_feed: Option<Pubkey>,
+    /// Price feed used instead of BCDEV sell course
+    pub bcdev_price_feed: Option<Pubkey>,
+    /// Max number of seconds since the last feed publish
+    pub max_price_age: i64,
+    /// Max feed confidence in basis points of the course
+    pub max_price_confidence: u64,
+    /// FCTR pricing mode
+    pub price_curve: PriceCurve,
+    /// Accepted SPL quote token mint
+    pub quote_mint: Option<Pubkey>,
+    /// Registrar quote token vault
+    pub quote_vault: Option<Pubkey>,
+    /// Quote tokens amount for one FCTR when buying
+    pub quote_buy_price: u64,
+    /// Quote tokens amount for one FCTR when selling back
+    pub quote_sell_price: u64,
+    /// FCTR amount bought for quote tokens and not sold back
+    pub quote_supply: u64,
+    /// FCTR amount bought for SOL and not sold back
+    pub sol_supply: u64,
+    /// Bump of the registrar SOL vault
+    pub sol_vault_bump: u8,
+    /// Trade fee in basis points
+    pub fee_bps: u64,
+    /// Account receiving trade fees
+    pub treasury: Pubkey,
+    /// Max FCTR supply
+    pub max_supply: Option<u64>,
+    /// Max FCTR amount bought by one member
+    pub max_per_member: Option<u64>,
+    /// Root of the members allowlist Merkle tree
+    pub merkle_root: Option<[u8; 32]>,
+    /// Referrer BCDEV bonus in basis points of bought FCTR
+    pub referral_bps: u64,
+    /// Lamports paid from crank tips vault for cranking next round
+    pub crank_tip: u64,
+    /// Trading pause flag
+    pub trading_paused: bool,
+    /// Staking pause flag
+    pub staking_paused: bool,
+    /// Sharing pause flag
+    pub sharing_paused: bool,
+    /// Authority proposed to accept ownership
+    pub pending_authority: Option<Pubkey>,
+    /// Time after current round end when members can unstake without reward
+    pub emergency_grace_period: i64,
+    /// Early unstake penalty in basis points of earned reward
+    pub early_unstake_penalty_bps: u64,
+    /// What happens with early unstake penalty
+    pub penalty_mode: PenaltyMode,
+}
+
+#[account]
+pub struct PriceFeed {
+    /// Account allowed to publish courses.
+    pub authority: Pubkey,
+    /// Token amount for one SOL
+    pub course: u64,
+    /// Confidence interval of the course
+    pub confidence: u64,
+    /// Last publish time
+    pub publish_ts: i64,
 }
 
 #[account]
@@ -1687,6 +3947,16 @@ pub struct Member {
     pub trusted_pool: Vec<MemberToMember>,
     /// Signer nonce.
     pub nonce: u8,
+    /// Cumulative bought FCTR
+    pub total_bought: u64,
+    /// Member who referred this member
+    pub referrer: Option<Pubkey>,
+    /// Number of referred members
+    pub referred_members: u32,
+    /// Cumulative referral bonus in BCDEV
+    pub referral_rewards: u64,
+    /// Time weighted stake shares in staked round
+    pub shares: u128,
 }
 
 #[account]
@@ -1701,6 +3971,36 @@ pub struct Round {
     pub number: u32,
     /// Finalyty flag
     pub final_round: bool,        
+    /// Round duration
+    pub duration: i64,
+    /// Base APR in percents multiplied 100000
+    pub base_apr: u64,
+    /// APR bonus for each truster in percents multiplied 100000
+    pub truster_apr: u64,
+    /// BCDEV budget distributed by stake shares
+    pub reward_budget: Option<u64>,
+    /// Sum of time weighted stake shares
+    pub total_shares: u128,
+    /// Total own FCTR staked in round
+    pub total_staked: u64,
+    /// Total trusted FCTR staked in round
+    pub total_trusted_staked: u64,
+    /// Number of members staked in round
+    pub stakers: u32,
+    /// Total BCDEV reward calculated by APR or round budget if it is set
+    pub reward_promised: u64,
+    /// Total BCDEV reward minted on unstake
+    pub reward_minted: u64,
+    /// Number of members staked in round and not unstaked yet
+    pub active_stakers: u32,
+    /// Number of not burned trust checks of the round
+    pub open_checks: u32,
+    /// Account who paid rent for the round
+    pub payer: Pubkey,
+    /// Early unstake penalties shared by remaining stakers of round without budget
+    pub penalty_pool: u64,
+    /// Budget part taken out of the round by early unstakes
+    pub budget_claimed: u64,
 }
 
 
@@ -1718,6 +4018,52 @@ pub struct TrustCheck {
     pub time_trusted: i64,    
     /// Burn status
     pub burn: bool,
+    /// Account who paid rent for the check
+    pub payer: Pubkey,
+}
+
+#[account]
+pub struct AdminMultisig {
+    /// Owners allowed to approve proposals
+    pub owners: Vec<Pubkey>,
+    /// Number of approvals to execute proposal
+    pub threshold: u64,
+    /// Nonce to derive the multisig signer
+    pub nonce: u8,
+}
+
+#[account]
+pub struct AdminProposal {
+    /// Multisig of the proposal
+    pub multisig: Pubkey,
+    /// Program to invoke
+    pub program_id: Pubkey,
+    /// Accounts of the instruction
+    pub accounts: Vec<ProposalAccount>,
+    /// Instruction data
+    pub data: Vec<u8>,
+    /// Approvals by owners index
+    pub signers: Vec<bool>,
+    /// Execution status
+    pub did_execute: bool,
+}
+
+#[account]
+pub struct StakePosition {
+    /// Member who staked
+    pub member: Pubkey,
+    /// Staked round
+    pub round: Pubkey,
+    /// Own staked amount
+    pub amount: u64,
+    /// Trusted staked amount
+    pub trusted_amount: u64,
+    /// Promised APR reward
+    pub reward: u64,
+    /// Time weighted stake shares
+    pub shares: u128,
+    /// Position address bump
+    pub bump: u8,
 }
 
 #[account]
@@ -1725,6 +4071,49 @@ pub struct TrusterToMember {
     pub last_time_trusted: i64,    
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum StakeAllocation {
+    /// Trusted tokens are staked before own
+    TrustedFirst,
+    /// Own tokens are staked before trusted
+    OwnFirst,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PenaltyMode {
+    /// Penalty is never minted
+    Burn,
+    /// Penalty is shared by remaining stakers of the round
+    Redistribute,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PriceCurve {
+    /// Courses from registrar or price feeds
+    Flat,
+    /// Lamports per FCTR grow by slope for each FCTR of supply
+    Linear {
+        base_price: u64,
+        slope: u64,
+    },
+    /// Lamports per FCTR double for each doubling_supply of FCTR
+    Exponential {
+        base_price: u64,
+        doubling_supply: u64,
+    },
+}
+
+impl PriceCurve {
+    fn is_valid(&self) -> bool {
+        match *self {
+            PriceCurve::Flat => true,
+            PriceCurve::Linear { base_price, .. } => base_price > 0,
+            PriceCurve::Exponential { base_price, doubling_supply } =>
+                base_price > 0 && doubling_supply > 0,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub struct MemberToMember {    
     /// Member who trust
@@ -1737,6 +4126,16 @@ pub struct MemberToMember {
     pub trusted_amount: u64,        
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalAccount {
+    /// Account key
+    pub pubkey: Pubkey,
+    /// Signer flag
+    pub is_signer: bool,
+    /// Writable flag
+    pub is_writable: bool,
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("The nonce given doesn't derive a valid program address.")]
@@ -1796,7 +4195,77 @@ pub enum ErrorCode {
     #[msg("Nor enouph tokens for stake ")] 
     NotEnouphForStake,
     #[msg("User does not have a stake ")] 
-    DidntStakedAnything    
+    DidntStakedAnything,
+    #[msg("Exchange course must be more then zero ")] 
+    InvalidCourse,
+    #[msg("Price feed account was not provided ")] 
+    PriceFeedNotProvided,
+    #[msg("Price feed account does not match registrar ")] 
+    PriceFeedWrongKey,
+    #[msg("Price feed course is too old ")] 
+    StalePriceFeed,
+    #[msg("Price feed confidence interval is too wide ")] 
+    PriceConfidenceTooWide,
+    #[msg("Bonding curve parameters are invalid ")] 
+    InvalidPriceCurve,
+    #[msg("Calculation overflow ")] 
+    MathOverflow,
+    #[msg("Quote token is not configured for registrar ")] 
+    QuoteNotConfigured,
+    #[msg("Requested amount is more then bought for quote tokens ")] 
+    QuoteSupplyExceeded,
+    #[msg("Trade amount is out of requested slippage bounds ")] 
+    SlippageExceeded,
+    #[msg("Fee can not be more then 100% ")] 
+    InvalidFee,
+    #[msg("Purchase exceeds max FCTR supply ")] 
+    MaxSupplyExceeded,
+    #[msg("Purchase exceeds max FCTR amount for member ")] 
+    MaxPerMemberExceeded,
+    #[msg("Beneficiary is not on the allowlist ")] 
+    NotAllowlisted,
+    #[msg("Referrer member is invalid ")] 
+    InvalidReferrer,
+    #[msg("Current round is not over yet ")] 
+    RoundIsNotOver,
+    #[msg("Round duration must be more then zero ")] 
+    InvalidRoundDuration,
+    #[msg("Round is still in use ")] 
+    RoundIsInUse,
+    #[msg("Trust check is not burned yet ")] 
+    TrustCheckNotBurned,
+    #[msg("Program is paused ")] 
+    ProgramPaused,
+    #[msg("Signer is not the pending authority ")] 
+    NotPendingAuthority,
+    #[msg("Threshold must be between one and owners number ")] 
+    InvalidThreshold,
+    #[msg("Signer is not multisig owner ")] 
+    NotMultisigOwner,
+    #[msg("Proposal is already executed ")] 
+    ProposalAlreadyExecuted,
+    #[msg("Not enough approvals for proposal ")] 
+    NotEnoughApprovals,
+    #[msg("Emergency unstake is available only while paused or stuck ")] 
+    EmergencyUnstakeNotAllowed,
+    #[msg("Grace period is out of allowed bounds ")] 
+    InvalidGracePeriod,
+    #[msg("Trusted tokens are already staked in other round ")] 
+    TrustedStakedInOtherRound,
+    #[msg("Position with trusted tokens must be unstaked ")] 
+    RestakeWithTrusters,
+    #[msg("Not enough FCTR bought for SOL ")] 
+    SolSupplyExceeded,
+    #[msg("Reward can not be compounded on bonding curve ")] 
+    CompoundWithCurve,
+    #[msg("Penalty can not be more then 100% ")] 
+    InvalidPenalty,
+    #[msg("Round is over, use unstake ")] 
+    RoundIsOver,
+    #[msg("Position with trusted tokens can not be unstaked early ")] 
+    EarlyUnstakeWithTrusters,
+    #[msg("Staked round belongs to other registrar ")] 
+    WrongStakedRound,
 }
 
 
@@ -1818,19 +4287,569 @@ pub struct ExitSharingEventLog {
     ts: i64,       
 }
 
+#[event]
+pub struct TradeEventLog {
+    member: Pubkey,
+    mint: Pubkey,
+    buy: bool,
+    amount: u64,
+    lamports: u64,
+    fee: u64,
+    ts: i64,
+}
+
+#[event]
+pub struct ReferralEventLog {
+    referrer: Pubkey,
+    member: Pubkey,
+    bonus: u64,
+    referred_members: u32,
+    referral_rewards: u64,
+    ts: i64,
+}
+
+#[event]
+pub struct ReferrerSetEventLog {
+    referrer: Pubkey,
+    member: Pubkey,
+    referred_members: u32,
+    ts: i64,
+}
+
+#[event]
+pub struct PricesEventLog {
+    registrar: Pubkey,
+    fctr_buy_course: u64,
+    fctr_sell_course: u64,
+    bcdev_sell_course: u64,
+    ts: i64,
+}
+
+#[event]
+pub struct AuthorityProposedEventLog {
+    registrar: Pubkey,
+    authority: Pubkey,
+    pending_authority: Pubkey,
+    ts: i64,
+}
+
+#[event]
+pub struct AuthorityAcceptedEventLog {
+    registrar: Pubkey,
+    old_authority: Pubkey,
+    new_authority: Pubkey,
+    ts: i64,
+}
+
+#[event]
+pub struct EmergencyUnstakeEventLog {
+    member: Pubkey,
+    amount: u64,
+    ts: i64,
+}
+
+#[event]
+pub struct EarlyUnstakeEventLog {
+    member: Pubkey,
+    round: Pubkey,
+    amount: u64,
+    reward: u64,
+    penalty: u64,
+    ts: i64,
+}
+
+#[event]
+pub struct PauseEventLog {
+    registrar: Pubkey,
+    trading_paused: bool,
+    staking_paused: bool,
+    sharing_paused: bool,
+    ts: i64,
+}
+
 fn curency_to_sol_in_lamports (
     amount_of_carency: &u64,
     carency: &u64, 
     course: &u64
-)-> u64 {      
-    let mut lamports_amount = amount_of_carency/(course*carency/LAMPORTS_PER_SOL);     
-    let reminder = amount_of_carency%(course*carency/LAMPORTS_PER_SOL);                 
+)-> Result<u64> {      
+    // Token units for one lamport, in u128 as 18 decimals BCDEV overflows u64
+    let units = *course as u128 * *carency as u128 / LAMPORTS_PER_SOL as u128;
+    require!(units > 0, ErrorCode::InvalidCourse);
+    let mut lamports_amount = *amount_of_carency as u128 / units;     
+    let reminder = *amount_of_carency as u128 % units;                 
          if reminder!=0{
              if ((lamports_amount * 10 )/reminder) > 4 {
                  lamports_amount+=1;
              } 
          }
-    lamports_amount       
+    u64::try_from(lamports_amount).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+fn feed_course<'info> (
+    registrar: &Registrar,
+    price_feed: &Option<Pubkey>,
+    course: u64,
+    remaining_accounts: &[AccountInfo<'info>],
+    clock: &Clock,
+)-> Result<u64> {
+    // Use registrar course if there is no price feed
+    let price_feed_key = match price_feed {
+        Some(key) => key,
+        None => return Ok(course),
+    };
+
+    // Price feed is the first remaining account
+    let price_feed_info = remaining_accounts.first()
+        .ok_or(error!(ErrorCode::PriceFeedNotProvided))?;
+    require!(price_feed_info.key == price_feed_key, ErrorCode::PriceFeedWrongKey);
+    let price_feed = Account::<PriceFeed>::try_from(price_feed_info)?;
+
+    // Check that course was published
+    require!(price_feed.course > 0, ErrorCode::InvalidCourse);
+
+    // Check that course is fresh
+    require!(
+        clock.unix_timestamp - price_feed.publish_ts <= registrar.max_price_age,
+        ErrorCode::StalePriceFeed
+    );
+
+    // Check that confidence is inside allowed basis points of course
+    require!(
+        price_feed.confidence as u128 * 10_000 <=
+            price_feed.course as u128 * registrar.max_price_confidence as u128,
+        ErrorCode::PriceConfidenceTooWide
+    );
+
+    Ok(price_feed.course)
+}
+
+// Sell FCTR tokens amount of member
+fn sell_fctr_tokens (
+    accounts: &mut SellFctr,
+    amount: u64,
+    min_lamports: u64,
+)-> Result<()> {
+
+    // Check that trading is not paused
+    require!(!accounts.registrar.trading_paused, ErrorCode::ProgramPaused);
+
+    // Check that member has no trusters
+    require!(
+        accounts.member.trusted_pool.len()==0,
+        ErrorCode::CantBuyOfSharing,
+    );
+    
+    // Check that member didn't share
+    require!(
+        accounts.member.shared==0,
+        ErrorCode::CantBuyOfSharing,
+    );
+    
+    // Check that tokens were bought for SOL
+    require!(
+        accounts.registrar.sol_supply >= amount,
+        ErrorCode::SolSupplyExceeded
+    );
+    
+    // Calculate lamports amount for tokens
+    let sol_amount = if accounts.registrar.price_curve == PriceCurve::Flat {
+        curency_to_sol_in_lamports(&amount,&FCTR,&accounts.registrar.fctr_sell_course)?
+    } else {
+        // Redeem the SOL backed supply decrease along the bonding curve
+        let supply = accounts.registrar.sol_supply;
+        curve_lamports(&accounts.registrar.price_curve, supply - amount, supply)?
+    };
+    msg!("Sol amount for {} FCTR is {}",amount,sol_amount);        
+    
+    // Calculate fee for treasury out of the price
+    let fee = fee_amount(sol_amount, accounts.registrar.fee_bps);
+    msg!("Fee amount is {}",fee);
+    
+    // Check that price didn't move under seller bound
+    require!(sol_amount - fee >= min_lamports, ErrorCode::SlippageExceeded);
+    
+    // Check that vault has enouph lamports for transfer  
+    if **accounts.vault_sol_account.try_borrow_lamports()? < sol_amount  {
+        return Err(error!(ErrorCode::InsufficientFundsForTransaction));
+    }
+    
+    // Transfer amount to member and fee to treasury
+    **accounts.vault_sol_account.try_borrow_mut_lamports()? -= sol_amount ;
+    **accounts.beneficiary.try_borrow_mut_lamports()? += sol_amount - fee ;  
+    **accounts.treasury.try_borrow_mut_lamports()? += fee ;
+    
+    // Burn seller FCTR tokens         
+    let seeds = &[
+        accounts.registrar.to_account_info().key.as_ref(),
+        accounts.member.to_account_info().key.as_ref(),
+        &[accounts.member.nonce],
+    ];
+    let member_signer = &[&seeds[..]];
+
+    // Burn pool tokens.
+    {
+        let cpi_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info().clone(),
+            token::Burn {
+                mint: accounts.mint.to_account_info(),
+                from: accounts.vault.to_account_info(),
+                authority: accounts.member_signer.to_account_info(),
+            },
+            member_signer,
+        );
+        token::burn(cpi_ctx, amount)?;
+    }       
+
+    // Decrease having amount of tokens
+    accounts.member.bought -= amount;
+    accounts.registrar.sol_supply -= amount;
+    
+    //Emit trade event
+    emit!(TradeEventLog {
+        member: *accounts.member.to_account_info().key,
+        mint: accounts.mint.key(),
+        buy: false,
+        amount,
+        lamports: sol_amount,
+        fee,
+        ts: accounts.clock.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+// Check Merkle proof with sorted pairs hashing
+fn verify_merkle_proof (
+    proof: &[[u8; 32]],
+    root: [u8; 32],
+    leaf: [u8; 32],
+)-> bool {
+    let mut computed = leaf;
+    for node in proof.iter() {
+        computed = if computed <= *node {
+            keccak::hashv(&[&computed, node]).0
+        } else {
+            keccak::hashv(&[node, &computed]).0
+        };
+    }
+    computed == root
+}
+
+// Check that purchase amount fits registrar supply caps
+fn check_supply_caps (
+    registrar: &Registrar,
+    supply: u64,
+    member_total_bought: u64,
+    amount: u64,
+)-> Result<()> {
+    if let Some(max_supply) = registrar.max_supply {
+        let new_supply = supply.checked_add(amount)
+            .ok_or(error!(ErrorCode::MathOverflow))?;
+        require!(new_supply <= max_supply, ErrorCode::MaxSupplyExceeded);
+    }
+    if let Some(max_per_member) = registrar.max_per_member {
+        let new_total_bought = member_total_bought.checked_add(amount)
+            .ok_or(error!(ErrorCode::MathOverflow))?;
+        require!(new_total_bought <= max_per_member, ErrorCode::MaxPerMemberExceeded);
+    }
+    Ok(())
+}
+
+// Calculate member APR in percents multiplied 100000 for the round
+fn stake_apr (
+    member: &Member,
+    round: &Round,
+)-> u64 {
+    //Add round truster bonus percents multiplied 100000 for each truster to APR
+    let add_apr = round.truster_apr * member.trusted_pool.len() as u64;
+
+    // Calculate increase of base APR for member who trust his tokens
+    let x_apr = if member.shared == 0 {
+        // Round base percents * 100000 if not shared
+        round.base_apr
+    } else {
+        // Or calculated in percents * 100000
+        part_calculation_x10_5(
+            &(member.bought + member.trusted - member.shared),
+            &member.shared,
+            &ACCURACY
+        )
+    };
+    x_apr + add_apr
+}
+
+// Convert BCDEV amount to FCTR by BCDEV sell and FCTR buy courses
+fn bcdev_to_fctr (
+    amount: u64,
+    fctr_course: u64,
+    bcdev_course: u64,
+)-> Result<u64> {
+    require!(bcdev_course > 0, ErrorCode::InvalidCourse);
+    let fctr = (amount as u128)
+        .checked_mul(fctr_course as u128)
+        .and_then(|v| v.checked_mul(FCTR as u128))
+        .ok_or(ErrorCode::MathOverflow)?
+        / (bcdev_course as u128 * BCDEV as u128);
+    u64::try_from(fctr).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Remove settled position from member counters
+fn settle_position (
+    member: &mut Member,
+    position: &StakePosition,
+    with_trusters: bool,
+) {
+    member.reward = member.reward.saturating_sub(position.reward);
+    member.shares = member.shares.saturating_sub(position.shares);
+    // Restore members bought amount
+    member.bought += position.amount;
+    member.staked -= position.amount;
+    member.staked_trusted -= position.trusted_amount;
+    if with_trusters {
+        // Zero member trusters pool
+        member.trusted_pool = vec![];
+        member.trusted = 0;
+    }
+    // Mark member as unstaked if no positions left
+    member.unstaked = member.staked == 0 && member.staked_trusted == 0;
+}
+
+// Split staked trusted amount between trusters by their trusted amounts
+fn split_staked_trusted (
+    trusted_pool: &[MemberToMember],
+    staked_trusted: u64,
+)-> Vec<u64> {
+    let total: u64 = trusted_pool.iter().map(|m_t_m| m_t_m.trusted_amount).sum();
+    let mut left = staked_trusted;
+    let mut parts = Vec::with_capacity(trusted_pool.len());
+    for (i, m_t_m) in trusted_pool.iter().enumerate() {
+        // Last truster takes the rounding rest
+        let part = if i + 1 == trusted_pool.len() || total == 0 {
+            left
+        } else {
+            (m_t_m.trusted_amount as u128 * staked_trusted as u128 / total as u128) as u64
+        };
+        let part = part.min(m_t_m.trusted_amount).min(left);
+        left -= part;
+        parts.push(part);
+    }
+    parts
+}
+
+// Load round account passed without type and check its registrar
+fn load_round<'info> (
+    info: &AccountInfo<'info>,
+    registrar: &Pubkey,
+)-> Result<Account<'info, Round>> {
+    let round = Account::<Round>::try_from(info)?;
+    require!(round.registrar == *registrar, ErrorCode::WrongStakedRound);
+    Ok(round)
+}
+
+// Remove staked trusted tokens taken back by trusters from member, position and round
+fn remove_trusted_stake (
+    member: &mut Member,
+    position: &mut StakePosition,
+    round: &mut Round,
+    amount: u64,
+    now: i64,
+) {
+    // Decrease counter for trusted stake
+    member.staked_trusted -= amount;
+    
+    // The decrease is for round truster bonus for the rest of the round
+    let staked_time = (round.stop_ts - now).max(0);
+    let spt_amount = apr_calculation (
+        &amount,
+        &round.truster_apr, 
+        &round.duration,
+        &staked_time,
+    );
+    msg!("{} percent part for {} FCTR is {} BCDEV",staked_time as f64 /round.duration as f64, amount, spt_amount);
+    
+    // Remove reward, time weighted shares and statistics of taken back tokens
+    let shares = stake_shares(amount, staked_time);
+    member.reward = member.reward.saturating_sub(spt_amount);
+    member.shares = member.shares.saturating_sub(shares);
+    round.total_shares = round.total_shares.saturating_sub(shares);
+    round.total_trusted_staked = round.total_trusted_staked.saturating_sub(amount);
+    if round.reward_budget.is_none() {
+        round.reward_promised = round.reward_promised.saturating_sub(spt_amount);
+    }
+    position.trusted_amount -= amount;
+    position.reward = position.reward.saturating_sub(spt_amount);
+    position.shares = position.shares.saturating_sub(shares);
+}
+
+// Calculate time weighted stake shares
+fn stake_shares (
+    amount: u64,
+    staked_time: i64,
+)-> u128 {
+    amount as u128 * staked_time.max(0) as u128
+}
+
+// Calculate member part of the round budget by shares
+fn budget_reward (
+    budget: u64,
+    shares: u128,
+    total_shares: u128,
+)-> Result<u64> {
+    if total_shares == 0 {
+        return Ok(0);
+    }
+    let reward = (budget as u128)
+        .checked_mul(shares)
+        .ok_or(ErrorCode::MathOverflow)?
+        / total_shares;
+    u64::try_from(reward).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Calculate position reward with its part of early unstake penalties
+fn position_reward (
+    round: &Round,
+    position: &StakePosition,
+)-> Result<u64> {
+    // Get position part of round budget left or position reward with part of penalties
+    match round.reward_budget {
+        Some(budget) => budget_reward(
+            budget - round.budget_claimed,
+            position.shares,
+            round.total_shares,
+        ),
+        None => Ok(
+            position.reward +
+                budget_reward(round.penalty_pool, position.shares, round.total_shares)?
+        ),
+    }
+}
+
+// Fill new round struct and move registrar to it
+#[allow(clippy::too_many_arguments)]
+fn open_round (
+    registrar: &mut Registrar,
+    registrar_key: Pubkey,
+    round: &mut Round,
+    round_key: Pubkey,
+    ts: i64,
+    final_round: bool,
+    duration: i64,
+    base_apr: u64,
+    truster_apr: u64,
+    reward_budget: Option<u64>,
+) {
+    round.registrar = registrar_key;
+    round.start_ts = ts;
+    round.stop_ts = ts + duration;
+    round.number = registrar.current_round + 1;
+    round.final_round = final_round;
+    round.duration = duration;
+    round.base_apr = base_apr;
+    round.truster_apr = truster_apr;
+    round.reward_budget = reward_budget;
+    round.total_shares = 0;
+    round.total_staked = 0;
+    round.total_trusted_staked = 0;
+    round.stakers = 0;
+    round.reward_promised = reward_budget.unwrap_or(0);
+    round.reward_minted = 0;
+    round.active_stakers = 0;
+    round.open_checks = 0;
+    round.penalty_pool = 0;
+    round.budget_claimed = 0;
+
+    registrar.current_round += 1;
+    registrar.current_round_account = Some(round_key);
+    registrar.finalized = final_round;
+}
+
+// Fee part of lamports amount
+fn fee_amount (
+    lamports: u64,
+    fee_bps: u64,
+)-> u64 {
+    (lamports as u128 * fee_bps as u128 / BPS as u128) as u64
+}
+
+// Quote tokens amount for FCTR amount by price for one FCTR
+fn fctr_to_quote (
+    amount: u64,
+    price: u64,
+    round_up: bool,
+)-> Result<u64> {
+    let value = amount as u128 * price as u128;
+    let mut quote_amount = value / FCTR as u128;
+    let reminder = value % FCTR as u128;
+    if round_up && reminder > 0 {
+        quote_amount += 1;
+    }
+    u64::try_from(quote_amount).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Lamports between two supply points of the bonding curve
+fn curve_lamports (
+    curve: &PriceCurve,
+    from_supply: u64,
+    to_supply: u64,
+)-> Result<u64> {
+    let lamports = curve_reserve(curve, to_supply)? - curve_reserve(curve, from_supply)?;
+    u64::try_from(lamports).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Lamports which back the supply on the bonding curve
+fn curve_reserve (
+    curve: &PriceCurve,
+    supply: u64,
+)-> Result<u128> {
+    let supply = supply as u128;
+    let fctr = FCTR as u128;
+    let reserve = match *curve {
+        PriceCurve::Flat => Some(0),
+        // base * s + slope * s^2 / 2
+        PriceCurve::Linear { base_price, slope } => {
+            let base_part = (base_price as u128).checked_mul(supply)
+                .map(|v| v / fctr);
+            let slope_part = (slope as u128).checked_mul(supply)
+                .map(|v| v / fctr)
+                .and_then(|v| v.checked_mul(supply))
+                .map(|v| v / (2 * fctr));
+            base_part.zip(slope_part).and_then(|(b, s)| b.checked_add(s))
+        },
+        // base * d * (2^(s/d) - 1) / ln2
+        PriceCurve::Exponential { base_price, doubling_supply } => {
+            supply.checked_mul(CURVE_PRECISION)
+                .map(|v| v / doubling_supply as u128)
+                .and_then(exp2_fixed)
+                .and_then(|v| (v - CURVE_PRECISION).checked_mul(base_price as u128))
+                .map(|v| v / CURVE_LN2)
+                .and_then(|v| v.checked_mul(doubling_supply as u128))
+                .map(|v| v / fctr)
+        },
+    };
+    reserve.ok_or(error!(ErrorCode::MathOverflow))
+}
+
+// Power of two for fixed point exponent
+fn exp2_fixed (
+    x: u128,
+)-> Option<u128> {
+    let int = x / CURVE_PRECISION;
+    if int >= 64 {
+        return None;
+    }
+
+    // Taylor series of e^(frac * ln2)
+    let y = (x % CURVE_PRECISION) * CURVE_LN2 / CURVE_PRECISION;
+    let mut term = CURVE_PRECISION;
+    let mut sum = CURVE_PRECISION;
+    for i in 1..20 {
+        term = term * y / CURVE_PRECISION / i;
+        if term == 0 {
+            break;
+        }
+        sum += term;
+    }
+    sum.checked_mul(1u128 << int)
 }
 
 fn part_calculation_x10_5 (    
@@ -5,6 +5,7 @@ import { FctrStaking } from "../target/types/fctr_staking";
 import { TOKEN_PROGRAM_ID, Token } from "@solana/spl-token";
 
 const serumCmn = require("@project-serum/common");
+const { keccak_256 } = require("js-sha3");
 const { assert, expect } = require("chai");
 
 describe("fctr-staking", () => {
@@ -26,6 +27,31 @@ describe("fctr-staking", () => {
   const registrar = anchor.web3.Keypair.generate();
   
   const roundTimelock = new anchor.BN(10);
+  
+  // Round APR parameters in percents multiplied 100000
+  const baseApr = new anchor.BN(100_000);
+  const trusterApr = new anchor.BN(200_000);
+  
+  // BCDEV budget distributed on round 2 by stake shares
+  const round2Budget = new anchor.BN(BCDEV/1000);
+  
+  // Find stake position address of member in round
+  const stakePosition = async (member, round) => {
+    const [position] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("position")),
+        member.toBuffer(),
+        round.toBuffer(),
+      ],
+      program.programId
+    );
+    return position;
+  };
+  
+  // Exchange courses: token amount for one SOL
+  const fctrBuyCourse = new anchor.BN(109);
+  const fctrSellCourse = new anchor.BN(101);
+  const bcdevSellCourse = new anchor.BN(11);
 
   let registrarAccount = null;
   
@@ -55,7 +81,10 @@ describe("fctr-staking", () => {
     
     //Find PDA for vaultAccount for SOL
     const [_vault_sol_account_pda, _vault_sol_account_bump] = await anchor.web3.PublicKey.findProgramAddress(
-      [Buffer.from(anchor.utils.bytes.utf8.encode("sol-seed"))],
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("sol-seed")),
+        registrar.publicKey.toBuffer(),
+      ],
       program.programId
     );
     vault_sol_account_pda = _vault_sol_account_pda;
@@ -79,11 +108,16 @@ describe("fctr-staking", () => {
       provider.wallet.publicKey,
       nonce,
       roundTimelock,     
+      fctrBuyCourse,
+      fctrSellCourse,
+      bcdevSellCourse,
+      { flat: {} },
       {
         accounts: {
           registrar: registrar.publicKey,  
           poolMint,
           vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
           payer: provider.wallet.publicKey,
           systemProgram: anchor.web3.SystemProgram.programId,
@@ -105,6 +139,49 @@ describe("fctr-staking", () => {
     assert.isTrue(registrarAccount.mint.equals(mint));
     assert.isTrue(registrarAccount.poolMint.equals(poolMint));         
     assert.isTrue(registrarAccount.roundTimelock.eq(roundTimelock));
+    assert.isTrue(registrarAccount.fctrBuyCourse.eq(fctrBuyCourse));
+    assert.isTrue(registrarAccount.fctrSellCourse.eq(fctrSellCourse));
+    assert.isTrue(registrarAccount.bcdevSellCourse.eq(bcdevSellCourse));
+    assert.ok(registrarAccount.priceCurve.flat);
+    assert.isTrue(registrarAccount.emergencyGracePeriod.eq(roundTimelock));
+    assert.strictEqual(registrarAccount.solVaultBump, vault_sol_account_bump);
+  });
+  
+  it("Changes exchange courses", async () => {
+    
+    await program.rpc.setPrices(
+      new anchor.BN(110),
+      new anchor.BN(100),
+      new anchor.BN(12),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.fctrBuyCourse.eq(new anchor.BN(110)));
+    assert.isTrue(registrarAccount.fctrSellCourse.eq(new anchor.BN(100)));
+    assert.isTrue(registrarAccount.bcdevSellCourse.eq(new anchor.BN(12)));
+    
+    // Restore initial courses
+    await program.rpc.setPrices(
+      fctrBuyCourse,
+      fctrSellCourse,
+      bcdevSellCourse,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
   });
   
   // Creating Anna member
@@ -137,7 +214,7 @@ describe("fctr-staking", () => {
     memberAnnaVault = await serumCmn.createTokenAccount(provider, mint, memberAnnaSigner);
    
     
-    const tx = program.transaction.createMember(nonce, false, {
+    const tx = program.transaction.createMember(nonce, false, [], {
       accounts: {
         registrar: registrar.publicKey,
         member: memberAnna.publicKey,
@@ -145,6 +222,7 @@ describe("fctr-staking", () => {
         memberSigner: memberAnnaSigner,        
         tokenProgram: TOKEN_PROGRAM_ID,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
         systemProgram: anchor.web3.SystemProgram.programId,  
       },      
     });
@@ -174,16 +252,20 @@ describe("fctr-staking", () => {
     let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
     
     await program.rpc.buyFctr(      
-          buyingAmount,      
+          buyingAmount,
+          new anchor.BN(SOL),
       {
         accounts: {          
           registrar:registrar.publicKey,
           registrarSigner,
           mint,
+          poolMint,
           buyer: Anna.publicKey,
           member: memberAnna.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -203,6 +285,575 @@ describe("fctr-staking", () => {
   
   
   
+  it("Rejects buying FCTR over slippage bound", async () => {
+    
+    let failed = false;
+    try {
+      await program.rpc.buyFctr(
+            new anchor.BN(10*FCTR),
+            new anchor.BN(1),
+        {
+          accounts: {
+            registrar:registrar.publicKey,
+            registrarSigner,
+            mint,
+            poolMint,
+            buyer: Anna.publicKey,
+            member: memberAnna.publicKey,
+            vaultSolAccount: vault_sol_account_pda,
+            treasury: provider.wallet.publicKey,
+            vault: memberAnnaVault,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [Anna],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "SlippageExceeded");
+    }
+    assert.isTrue(failed);
+  });
+  
+  it("Rejects buying FCTR over member cap", async () => {
+    
+    let memberAccount = await program.account.member.fetch(
+      memberAnna.publicKey
+    );
+    assert.isTrue(memberAccount.totalBought.eq(new anchor.BN(15*FCTR)));
+    
+    // Anna can buy only 5 more FCTR
+    await program.rpc.setSupplyCaps(
+      null,
+      new anchor.BN(20*FCTR),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    let failed = false;
+    try {
+      await program.rpc.buyFctr(
+            new anchor.BN(10*FCTR),
+            new anchor.BN(SOL),
+        {
+          accounts: {
+            registrar:registrar.publicKey,
+            registrarSigner,
+            mint,
+            poolMint,
+            buyer: Anna.publicKey,
+            member: memberAnna.publicKey,
+            vaultSolAccount: vault_sol_account_pda,
+            treasury: provider.wallet.publicKey,
+            vault: memberAnnaVault,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [Anna],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "MaxPerMemberExceeded");
+    }
+    assert.isTrue(failed);
+    
+    // Remove caps
+    await program.rpc.setSupplyCaps(
+      null,
+      null,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+  });
+  
+  it("Rejects buying FCTR while trading is paused", async () => {
+    
+    await program.rpc.setPause(
+      true,
+      false,
+      false,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    let registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.ok(registrarAccount.tradingPaused);
+    assert.ok(!registrarAccount.stakingPaused);
+    
+    let failed = false;
+    try {
+      await program.rpc.buyFctr(
+            new anchor.BN(10*FCTR),
+            new anchor.BN(SOL),
+        {
+          accounts: {
+            registrar:registrar.publicKey,
+            registrarSigner,
+            mint,
+            poolMint,
+            buyer: Anna.publicKey,
+            member: memberAnna.publicKey,
+            vaultSolAccount: vault_sol_account_pda,
+            treasury: provider.wallet.publicKey,
+            vault: memberAnnaVault,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [Anna],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "ProgramPaused");
+    }
+    assert.isTrue(failed);
+    
+    // Resume trading
+    await program.rpc.setPause(
+      false,
+      false,
+      false,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+  });
+  
+  it("Transfers registrar authority and takes it back", async () => {
+    
+    const newAuthority = anchor.web3.Keypair.generate();
+    
+    await program.rpc.proposeAuthority(
+      newAuthority.publicKey,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    let registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.pendingAuthority.equals(newAuthority.publicKey));
+    assert.isTrue(registrarAccount.authority.equals(provider.wallet.publicKey));
+    
+    await program.rpc.acceptAuthority(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          newAuthority: newAuthority.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        signers: [newAuthority],
+      }
+    );
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.authority.equals(newAuthority.publicKey));
+    assert.isNull(registrarAccount.pendingAuthority);
+    
+    // Give authority back to the wallet
+    await program.rpc.proposeAuthority(
+      provider.wallet.publicKey,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: newAuthority.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+        signers: [newAuthority],
+      }
+    );
+    
+    await program.rpc.acceptAuthority(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          newAuthority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.authority.equals(provider.wallet.publicKey));
+  });
+  
+  it("Controls registrar by 2 of 3 admin multisig", async () => {
+    
+    const multisig = anchor.web3.Keypair.generate();
+    const ownerB = anchor.web3.Keypair.generate();
+    const ownerC = anchor.web3.Keypair.generate();
+    
+    const [multisigSigner, multisigNonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [multisig.publicKey.toBuffer()],
+      program.programId
+    );
+    
+    await program.rpc.createMultisig(
+      [provider.wallet.publicKey, ownerB.publicKey, ownerC.publicKey],
+      new anchor.BN(2),
+      multisigNonce,
+      {
+        accounts: {
+          multisig: multisig.publicKey,
+        },
+        signers: [multisig],
+        instructions: [
+          await program.account.adminMultisig.createInstruction(multisig, 200),
+        ],
+      }
+    );
+    
+    // Propose multisig signer as new registrar authority
+    await program.rpc.proposeAuthority(
+      multisigSigner,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    // Propose, approve and execute instruction by multisig
+    const executeByMultisig = async (ix) => {
+      const proposal = anchor.web3.Keypair.generate();
+      await program.rpc.createProposal(
+        ix.programId,
+        ix.keys,
+        ix.data,
+        {
+          accounts: {
+            multisig: multisig.publicKey,
+            proposal: proposal.publicKey,
+            proposer: provider.wallet.publicKey,
+          },
+          signers: [proposal],
+          instructions: [
+            await program.account.adminProposal.createInstruction(proposal, 1000),
+          ],
+        }
+      );
+      
+      // One approval is not enough
+      let failed = false;
+      try {
+        await program.rpc.executeProposal({
+          accounts: {
+            multisig: multisig.publicKey,
+            multisigSigner,
+            proposal: proposal.publicKey,
+          },
+          remainingAccounts: ix.keys
+            .map((meta) => meta.pubkey.equals(multisigSigner) ? { ...meta, isSigner: false } : meta)
+            .concat({ pubkey: program.programId, isWritable: false, isSigner: false }),
+        });
+      } catch (err) {
+        failed = true;
+        assert.strictEqual(err.error.errorCode.code, "NotEnoughApprovals");
+      }
+      assert.isTrue(failed);
+      
+      await program.rpc.approveProposal({
+        accounts: {
+          multisig: multisig.publicKey,
+          proposal: proposal.publicKey,
+          owner: ownerB.publicKey,
+        },
+        signers: [ownerB],
+      });
+      
+      await program.rpc.executeProposal({
+        accounts: {
+          multisig: multisig.publicKey,
+          multisigSigner,
+          proposal: proposal.publicKey,
+        },
+        remainingAccounts: ix.keys
+          .map((meta) => meta.pubkey.equals(multisigSigner) ? { ...meta, isSigner: false } : meta)
+          .concat({ pubkey: program.programId, isWritable: false, isSigner: false }),
+      });
+      
+      let proposalAccount = await program.account.adminProposal.fetch(
+        proposal.publicKey
+      );
+      assert.ok(proposalAccount.didExecute);
+    };
+    
+    await executeByMultisig(
+      program.instruction.acceptAuthority({
+        accounts: {
+          registrar: registrar.publicKey,
+          newAuthority: multisigSigner,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      })
+    );
+    
+    let registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.authority.equals(multisigSigner));
+    
+    // Give authority back to the wallet by multisig
+    await executeByMultisig(
+      program.instruction.proposeAuthority(
+        provider.wallet.publicKey,
+        {
+          accounts: {
+            registrar: registrar.publicKey,
+            authority: multisigSigner,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          },
+        }
+      )
+    );
+    
+    await program.rpc.acceptAuthority(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          newAuthority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.authority.equals(provider.wallet.publicKey));
+  });
+  
+  const treasury = anchor.web3.Keypair.generate();
+  
+  it("Anna buy FCTR with fee to treasury", async () => {
+    
+    // Fund treasury to be rent exempt
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(treasury.publicKey, solAmount),
+      "processed"
+    );
+    
+    // 1% fee
+    await program.rpc.setFee(
+      new anchor.BN(100),
+      treasury.publicKey,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    let _treasury_before = await provider.connection.getBalance(treasury.publicKey);
+    
+    await program.rpc.buyFctr(
+          new anchor.BN(109*FCTR/10),
+          new anchor.BN(SOL/5),
+      {
+        accounts: {
+          registrar:registrar.publicKey,
+          registrarSigner,
+          mint,
+          poolMint,
+          buyer: Anna.publicKey,
+          member: memberAnna.publicKey,
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: treasury.publicKey,
+          vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [Anna],
+      }
+    );
+    
+    // 1% of 0.1 SOL for 10.9 FCTR
+    let _treasury_after = await provider.connection.getBalance(treasury.publicKey);
+    assert.strictEqual(_treasury_after - _treasury_before, SOL/1000);
+    
+    // Return treasury to owner without fee
+    await program.rpc.setFee(
+      new anchor.BN(0),
+      provider.wallet.publicKey,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+  });
+  
+  const fctrPriceFeed = anchor.web3.Keypair.generate();
+  
+  it("Anna buy FCTR by price feed course", async () => {
+    
+    // Create price feed published by provider wallet
+    await program.rpc.createPriceFeed(
+      provider.wallet.publicKey,
+      {
+        accounts: {
+          priceFeed: fctrPriceFeed.publicKey,
+        },
+        signers: [fctrPriceFeed],
+        instructions: [
+          await program.account.priceFeed.createInstruction(fctrPriceFeed),
+        ],
+      }
+    );
+    
+    // Publish 100 FCTR for one SOL course
+    await program.rpc.updatePriceFeed(
+      new anchor.BN(100),
+      new anchor.BN(1),
+      {
+        accounts: {
+          priceFeed: fctrPriceFeed.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
+    
+    // Point registrar at the price feed
+    await program.rpc.setPriceFeeds(
+      fctrPriceFeed.publicKey,
+      null,
+      new anchor.BN(60),
+      new anchor.BN(100),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
+    
+    await program.rpc.buyFctr(
+          new anchor.BN(10*FCTR),
+          new anchor.BN(SOL),
+      {
+        accounts: {
+          registrar:registrar.publicKey,
+          registrarSigner,
+          mint,
+          poolMint,
+          buyer: Anna.publicKey,
+          member: memberAnna.publicKey,
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
+          vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        remainingAccounts: [
+          { pubkey: fctrPriceFeed.publicKey, isWritable: false, isSigner: false },
+        ],
+        signers: [Anna],
+      }
+    );
+    
+    // 10 FCTR for 100 FCTR per SOL course
+    let _vault_sol_after = await provider.connection.getBalance(vault_sol_account_pda);
+    assert.strictEqual(_vault_sol_after - _vault_sol_before, SOL/10);
+    
+    // Return to registrar courses
+    await program.rpc.setPriceFeeds(
+      null,
+      null,
+      new anchor.BN(0),
+      new anchor.BN(0),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+  });
+  
+  it("Sell some FCTR", async () => {
+    
+    let sellingAmount = new anchor.BN(5*FCTR);
+    
+    let memberAccount = await program.account.member.fetch(
+      memberAnna.publicKey
+    );
+    let boughtBefore = memberAccount.bought;
+    
+    await program.rpc.sellFctr(
+        sellingAmount,
+        new anchor.BN(0),
+      {
+        accounts: {
+          registrar:registrar.publicKey,
+          memberSigner: memberAnnaSigner,
+          mint,
+          beneficiary: Anna.publicKey,
+          member: memberAnna.publicKey,
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
+          vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [Anna],
+      }
+    );
+    
+    memberAccount = await program.account.member.fetch(
+      memberAnna.publicKey
+    );
+    assert.isTrue(memberAccount.bought.eq(boughtBefore.sub(sellingAmount)));
+    
+    const memberVault = await serumCmn.getTokenAccount(
+      provider,
+      memberAnnaVault
+    );
+    assert.isTrue(memberVault.amount.eq(memberAccount.bought));
+  });
+  
   it("Sell all FCTR", async () => {
       
     // Airdropping tokens to a registrar vault .
@@ -214,6 +865,7 @@ describe("fctr-staking", () => {
     let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
     
     await program.rpc.sellAllFctr(    
+        new anchor.BN(0),
       {
         accounts: {     
           registrar:registrar.publicKey,
@@ -221,8 +873,10 @@ describe("fctr-staking", () => {
           mint,
           beneficiary: Anna.publicKey,
           member: memberAnna.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -241,6 +895,124 @@ describe("fctr-staking", () => {
    assert.isTrue(memberVault.amount.eq(new anchor.BN(0)));
   });
   
+  let quoteMint = null;
+  let quoteVault = null;
+  let annaQuoteAccount = null;
+  
+  it("Buy and sell FCTR for quote tokens", async () => {
+    
+    // Quote token with 6 decimals and Anna account with 100 tokens
+    const [_quoteMint, _annaQuoteAccount] = await serumCmn.createMintAndVault(
+      provider,
+      new anchor.BN(100_000_000),
+      Anna.publicKey,
+      6
+    );
+    quoteMint = _quoteMint;
+    annaQuoteAccount = _annaQuoteAccount;
+    quoteVault = await serumCmn.createTokenAccount(provider, quoteMint, registrarSigner);
+    
+    // 2 quote tokens for FCTR on buy and 1.9 on sell
+    await program.rpc.setQuoteMint(
+      new anchor.BN(2_000_000),
+      new anchor.BN(1_900_000),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          registrarSigner,
+          quoteMint,
+          quoteVault,
+        },
+      }
+    );
+    
+    await program.rpc.buyFctrWithQuote(
+      new anchor.BN(10*FCTR),
+      new anchor.BN(20_000_000),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          registrarSigner,
+          mint,
+          buyer: Anna.publicKey,
+          member: memberAnna.publicKey,
+          buyerQuoteAccount: annaQuoteAccount,
+          quoteVault,
+          vault: memberAnnaVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [Anna],
+      }
+    );
+    
+    let vault = await serumCmn.getTokenAccount(provider, quoteVault);
+    assert.isTrue(vault.amount.eq(new anchor.BN(20_000_000)));
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.quoteSupply.eq(new anchor.BN(10*FCTR)));
+    assert.isTrue(registrarAccount.solSupply.eq(new anchor.BN(0)));
+    
+    // FCTR bought for quote tokens is not backed by SOL vault
+    let failed = false;
+    try {
+      await program.rpc.sellFctr(
+        new anchor.BN(10*FCTR),
+        new anchor.BN(0),
+        {
+          accounts: {     
+            registrar:registrar.publicKey,
+            memberSigner: memberAnnaSigner,
+            mint,
+            beneficiary: Anna.publicKey,
+            member: memberAnna.publicKey,
+            vaultSolAccount: vault_sol_account_pda,
+            treasury: provider.wallet.publicKey,
+            vault: memberAnnaVault,
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,  
+          },
+          signers: [Anna],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "SolSupplyExceeded");
+    }
+    assert.isTrue(failed);
+    
+    await program.rpc.sellFctrForQuote(
+      new anchor.BN(10*FCTR),
+      new anchor.BN(19_000_000),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          registrarSigner,
+          memberSigner: memberAnnaSigner,
+          mint,
+          beneficiary: Anna.publicKey,
+          member: memberAnna.publicKey,
+          beneficiaryQuoteAccount: annaQuoteAccount,
+          quoteVault,
+          vault: memberAnnaVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [Anna],
+      }
+    );
+    
+    vault = await serumCmn.getTokenAccount(provider, quoteVault);
+    assert.isTrue(vault.amount.eq(new anchor.BN(1_000_000)));
+    
+    let memberAccount = await program.account.member.fetch(
+      memberAnna.publicKey
+    );
+    assert.isTrue(memberAccount.bought.eq(new anchor.BN(0)));
+  });
+  
   const round1 = anchor.web3.Keypair.generate();    
   
   it("Create round", async () => {
@@ -248,6 +1020,10 @@ describe("fctr-staking", () => {
       
     await program.rpc.startRound(    
         final_round,
+        roundTimelock,
+        baseApr,
+        trusterApr,
+        null,
       {
         accounts: {     
            registrar: registrar.publicKey,
@@ -271,6 +1047,9 @@ describe("fctr-staking", () => {
     assert.isTrue(roundAccount.registrar.equals(registrar.publicKey));
     assert.strictEqual(roundAccount.number, 1);
     assert.ok(roundAccount.finalRound==false);   
+    assert.isTrue(roundAccount.duration.eq(roundTimelock));
+    assert.isTrue(roundAccount.baseApr.eq(baseApr));
+    assert.isTrue(roundAccount.trusterApr.eq(trusterApr));
   });
   
   //Create BCDEV account for Anna  
@@ -281,16 +1060,20 @@ describe("fctr-staking", () => {
     let buyingAmount = new anchor.BN(10*FCTR);
     
     await program.rpc.buyFctr(      
-          buyingAmount,      
+          buyingAmount,
+          new anchor.BN(SOL),
       {
         accounts: {          
           registrar:registrar.publicKey,
           registrarSigner,
           mint,
+          poolMint,
           buyer: Anna.publicKey,
           member: memberAnna.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -298,7 +1081,10 @@ describe("fctr-staking", () => {
       }
     );     
     
+    // Anna keeps half of bought FCTR liquid
     await program.rpc.stake(            
+      new anchor.BN(5*FCTR),
+      { ownFirst: {} },
       {
         accounts: {     
            registrar: registrar.publicKey,           
@@ -309,6 +1095,7 @@ describe("fctr-staking", () => {
            memberSigner: memberAnnaSigner,
            memberFctrVault: memberAnnaVault,           
            round: round1.publicKey,           
+           position: await stakePosition(memberAnna.publicKey, round1.publicKey),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,            
@@ -322,17 +1109,25 @@ describe("fctr-staking", () => {
       memberAnnaVault
     );
    
-   assert.isTrue(memberVault.amount.eq(new anchor.BN(0)));
+   assert.isTrue(memberVault.amount.eq(new anchor.BN(5*FCTR)));
    
    let memberAccount = await program.account.member.fetch(
       memberAnna.publicKey
     );
    
-   assert.isTrue(memberAccount.bought.eq(new anchor.BN(0)));
+   assert.isTrue(memberAccount.bought.eq(new anchor.BN(5*FCTR)));
    assert.isTrue(memberAccount.trusted.eq(new anchor.BN(0)));
-   assert.isTrue(memberAccount.staked.eq(buyingAmount));
+   assert.isTrue(memberAccount.staked.eq(new anchor.BN(5*FCTR)));
    assert.isTrue(memberAccount.stakedTrusted.eq(new anchor.BN(0)));
    assert.isTrue(memberAccount.shared.eq(new anchor.BN(0)));
+   
+   let positionAccount = await program.account.stakePosition.fetch(
+      await stakePosition(memberAnna.publicKey, round1.publicKey)
+    );
+   assert.isTrue(positionAccount.member.equals(memberAnna.publicKey));
+   assert.isTrue(positionAccount.round.equals(round1.publicKey));
+   assert.isTrue(positionAccount.amount.eq(new anchor.BN(5*FCTR)));
+   assert.isTrue(positionAccount.reward.eq(memberAccount.reward));
   });
      
   it("Buy 5 more FCTR for Anna to increase stake", async () => {
@@ -340,16 +1135,20 @@ describe("fctr-staking", () => {
     let buyingAmount = new anchor.BN(10*FCTR);    
     
     await program.rpc.buyFctr(      
-          buyingAmount,      
+          buyingAmount,
+          new anchor.BN(SOL),
       {
         accounts: {          
           registrar:registrar.publicKey,
           registrarSigner,
           mint,
+          poolMint,
           buyer: Anna.publicKey,
           member: memberAnna.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberAnnaVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -361,9 +1160,9 @@ describe("fctr-staking", () => {
       memberAnna.publicKey
     );
    
-   assert.isTrue(memberAccount.bought.eq(new anchor.BN(10*FCTR)));   
+   assert.isTrue(memberAccount.bought.eq(new anchor.BN(15*FCTR)));   
    assert.isTrue(memberAccount.trusted.eq(new anchor.BN(0)));
-   assert.isTrue(memberAccount.staked.eq(new anchor.BN(10*FCTR)));
+   assert.isTrue(memberAccount.staked.eq(new anchor.BN(5*FCTR)));
    assert.isTrue(memberAccount.stakedTrusted.eq(new anchor.BN(0)));
    assert.isTrue(memberAccount.shared.eq(new anchor.BN(0)));
   });
@@ -372,6 +1171,8 @@ describe("fctr-staking", () => {
   it("Anna Stake's to round 2 her additionaly bought 10 FCTR", async () => {  
 
     await program.rpc.stake(            
+      new anchor.BN(15*FCTR),
+      { ownFirst: {} },
       {
         accounts: {     
            registrar: registrar.publicKey,
@@ -382,6 +1183,7 @@ describe("fctr-staking", () => {
            memberSigner: memberAnnaSigner,
            memberFctrVault: memberAnnaVault,          
            round: round1.publicKey,           
+           position: await stakePosition(memberAnna.publicKey, round1.publicKey),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,  
@@ -416,6 +1218,10 @@ describe("fctr-staking", () => {
       
     await program.rpc.startRound(    
         final_round,
+        roundTimelock,
+        baseApr,
+        trusterApr,
+        round2Budget,
       {
         accounts: {     
            registrar: registrar.publicKey,
@@ -438,6 +1244,8 @@ describe("fctr-staking", () => {
     assert.isTrue(roundAccount.registrar.equals(registrar.publicKey));
     assert.strictEqual(roundAccount.number, 2);
     assert.ok(roundAccount.finalRound==false);   
+    assert.isTrue(roundAccount.rewardBudget.eq(round2Budget));
+    assert.isTrue(roundAccount.rewardPromised.eq(round2Budget));
   });
   
    
@@ -455,9 +1263,11 @@ describe("fctr-staking", () => {
            poolMint,           
            beneficiary: Anna.publicKey,
            member: memberAnna.publicKey,           
+           memberSigner: memberAnnaSigner,
            memberFctrVault: memberAnnaVault,
            memberBcdevVault: memberAnnaBcdevVault,
            round: round1.publicKey,           
+           position: await stakePosition(memberAnna.publicKey, round1.publicKey),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId, 
@@ -488,23 +1298,85 @@ describe("fctr-staking", () => {
        memberAnnaBcdevVault
      );
     
-    assert.isTrue(memberBcdevVault_after.amount.gt(new anchor.BN(0)));
+    assert.isTrue(memberBcdevVault_after.amount.gt(new anchor.BN(0)));
+    
+    // Settled position is closed
+    assert.isNull(
+      await provider.connection.getAccountInfo(
+        await stakePosition(memberAnna.publicKey, round1.publicKey)
+      )
+    );
+    
+    // Round statistics count Anna once for both stakes
+    let roundAccount = await program.account.round.fetch(
+      round1.publicKey
+    );
+    assert.strictEqual(roundAccount.stakers, 1);
+    assert.isTrue(roundAccount.totalStaked.eq(new anchor.BN(20*FCTR)));
+    assert.isTrue(roundAccount.totalTrustedStaked.eq(new anchor.BN(0)));
+    assert.isTrue(roundAccount.rewardPromised.gt(new anchor.BN(0)));
+    assert.isTrue(roundAccount.rewardMinted.eq(memberBcdevVault_after.amount));
+  });
+  
+  
+  it("Sell BCDEV", async () => {
+      
+    // Airdropping tokens to a donee.
+    await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(vault_sol_account_pda, 1*SOL),
+        "processed"
+    );
+    
+    let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
+    let amount = new anchor.BN(BCDEV/10000);
+
+    await program.rpc.sellBcdev(    
+        amount,
+        new anchor.BN(0),
+      {
+        accounts: {     
+          registrar:registrar.publicKey,
+          memberSigner: memberAnnaSigner,
+          poolMint,
+          beneficiary: Anna.publicKey,
+          member: memberAnna.publicKey,
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
+          vaultBcdev: memberAnnaBcdevVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [Anna],
+      }
+    );    
+    
+   let _vault_sol_after = await provider.connection.getBalance(vault_sol_account_pda);
+   assert.ok(_vault_sol_before !=_vault_sol_after);      
+   
   });
   
-  
-  it("Sell BCDEV", async () => {
-      
-    // Airdropping tokens to a donee.
-    await provider.connection.confirmTransaction(
-        await provider.connection.requestAirdrop(vault_sol_account_pda, 1*SOL),
-        "processed"
+  it("Sell BCDEV by large course", async () => {
+    
+    // Course * BCDEV decimals does not fit u64
+    await program.rpc.setPrices(
+      fctrBuyCourse,
+      fctrSellCourse,
+      new anchor.BN(1000),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
     );
     
     let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
-    let amount = new anchor.BN(BCDEV/10000);
-
+    
     await program.rpc.sellBcdev(    
-        amount,
+        new anchor.BN(BCDEV/100000),
+        new anchor.BN(0),
       {
         accounts: {     
           registrar:registrar.publicKey,
@@ -512,8 +1384,10 @@ describe("fctr-staking", () => {
           poolMint,
           beneficiary: Anna.publicKey,
           member: memberAnna.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vaultBcdev: memberAnnaBcdevVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -521,9 +1395,22 @@ describe("fctr-staking", () => {
       }
     );    
     
-   let _vault_sol_after = await provider.connection.getBalance(vault_sol_account_pda);
-   assert.ok(_vault_sol_before !=_vault_sol_after);      
-   
+    let _vault_sol_after = await provider.connection.getBalance(vault_sol_account_pda);
+    assert.ok(_vault_sol_before > _vault_sol_after);
+    
+    // Restore initial courses
+    await program.rpc.setPrices(
+      fctrBuyCourse,
+      fctrSellCourse,
+      bcdevSellCourse,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        },
+      }
+    );
   });
   
   // Creating bob member
@@ -532,6 +1419,36 @@ describe("fctr-staking", () => {
   let memberBobSigner = null;
   let memberBobVault = null;
   
+  // Allowlist of Bob and Charlie
+  let leafBob = null;
+  let leafCharlie = null;
+  
+  it("Sets members allowlist", async () => {
+    
+    leafBob = Buffer.from(keccak_256.arrayBuffer(Bob.publicKey.toBuffer()));
+    leafCharlie = Buffer.from(keccak_256.arrayBuffer(Charlie.publicKey.toBuffer()));
+    
+    // Root of the sorted pair
+    const root = Buffer.compare(leafBob, leafCharlie) <= 0 ?
+      keccak_256.arrayBuffer(Buffer.concat([leafBob, leafCharlie])) :
+      keccak_256.arrayBuffer(Buffer.concat([leafCharlie, leafBob]));
+    
+    await program.rpc.setMerkleRoot(
+      [...Buffer.from(root)],
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(Buffer.from(registrarAccount.merkleRoot).equals(Buffer.from(root)));
+  });
+  
   
   it("Creates a member Bob", async () => {
     let memberAccount = null;  
@@ -551,7 +1468,7 @@ describe("fctr-staking", () => {
     memberBobVault = await serumCmn.createTokenAccount(provider, mint, memberBobSigner);
     
 
-    const tx = program.transaction.createMember(nonce, false, {
+    const tx = program.transaction.createMember(nonce, false, [[...leafCharlie]], {
       accounts: {
         registrar: registrar.publicKey,
         member: memberBob.publicKey,
@@ -559,6 +1476,7 @@ describe("fctr-staking", () => {
         memberSigner: memberBobSigner,        
         tokenProgram: TOKEN_PROGRAM_ID,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
         systemProgram: anchor.web3.SystemProgram.programId,  
       },      
     });
@@ -582,16 +1500,20 @@ describe("fctr-staking", () => {
     let buyingAmount = new anchor.BN(10*FCTR);    
     
     await program.rpc.buyFctr(      
-          buyingAmount,      
+          buyingAmount,
+          new anchor.BN(SOL),
       {
         accounts: {          
           registrar:registrar.publicKey,
           registrarSigner,
           mint,
+          poolMint,
           buyer: Bob.publicKey,
           member: memberBob.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberBobVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -633,7 +1555,8 @@ describe("fctr-staking", () => {
     memberCharlieVault = await serumCmn.createTokenAccount(provider, mint, memberCharlieSigner);    
     memberCharlieBcdevVault = await serumCmn.createTokenAccount(provider, poolMint, memberCharlieSigner);  
     
-    const tx = program.transaction.createMember(nonce, false, {
+    // Charlie is referred by Anna
+    const tx = program.transaction.createMember(nonce, false, [[...leafBob]], {
       accounts: {
         registrar: registrar.publicKey,
         member: memberCharlie.publicKey,
@@ -641,8 +1564,12 @@ describe("fctr-staking", () => {
         memberSigner: memberCharlieSigner,        
         tokenProgram: TOKEN_PROGRAM_ID,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
         systemProgram: anchor.web3.SystemProgram.programId,  
       },
+      remainingAccounts: [
+        { pubkey: memberAnna.publicKey, isWritable: true, isSigner: false },
+      ],
       
     });
 
@@ -664,21 +1591,40 @@ describe("fctr-staking", () => {
   it("Buy 15 FCTR for Charlie", async () => {
       
     let buyingAmount = new anchor.BN(15*FCTR);    
+    
+    // 1% referral bonus
+    await program.rpc.setReferralBps(
+      new anchor.BN(100),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
   
     await program.rpc.buyFctr(      
-          buyingAmount,      
+          buyingAmount,
+          new anchor.BN(SOL),
       {
         accounts: {          
           registrar:registrar.publicKey,
           registrarSigner,
           mint,
+          poolMint,
           buyer: Charlie.publicKey,
           member: memberCharlie.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           vault: memberCharlieVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
+        remainingAccounts: [
+          { pubkey: memberAnna.publicKey, isWritable: true, isSigner: false },
+          { pubkey: memberAnnaBcdevVault, isWritable: true, isSigner: false },
+        ],
         signers: [Charlie],
       }
     );  
@@ -692,7 +1638,72 @@ describe("fctr-staking", () => {
    assert.isTrue(memberAccount.staked.eq(new anchor.BN(0)));
    assert.isTrue(memberAccount.stakedTrusted.eq(new anchor.BN(0)));
    assert.isTrue(memberAccount.shared.eq(new anchor.BN(0)));
+   assert.isTrue(memberAccount.referrer.equals(memberAnna.publicKey));
+   
+   // Anna got BCDEV redeemable for 1% of 137614679 lamports paid for 15 FCTR
+   let referrerAccount = await program.account.member.fetch(
+      memberAnna.publicKey
+    );
+   assert.strictEqual(referrerAccount.referredMembers, 1);
+   assert.isTrue(referrerAccount.referralRewards.eq(new anchor.BN("15137614690000000")));
+   
+    // Remove referral bonus
+    await program.rpc.setReferralBps(
+      new anchor.BN(0),
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+  });
+  
+  it("Removes members allowlist", async () => {
+    
+    await program.rpc.setMerkleRoot(
+      null,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+  });
+  
+  it("Rejects member referred by own beneficiary", async () => {
+    
+    const memberAnna2 = anchor.web3.Keypair.generate();
+    const [memberAnna2Signer, nonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [registrar.publicKey.toBuffer(), memberAnna2.publicKey.toBuffer()],
+        program.programId
+      );
     
+    let failed = false;
+    try {
+      await program.rpc.createMember(nonce, false, [], {
+        accounts: {
+          registrar: registrar.publicKey,
+          member: memberAnna2.publicKey,
+          beneficiary: Anna.publicKey,
+          memberSigner: memberAnna2Signer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        remainingAccounts: [
+          { pubkey: memberAnna.publicKey, isWritable: true, isSigner: false },
+        ],
+        signers: [memberAnna2, Anna],
+      });
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "InvalidReferrer");
+    }
+    assert.isTrue(failed);
   });
   
   let AnnaToBobRound2Check = anchor.web3.Keypair.generate(); 
@@ -725,6 +1736,7 @@ describe("fctr-staking", () => {
           trustCheck: AnnaToBobRound2Check.publicKey,          
           registrarVault,
           round: round2.publicKey,                    
+          position: await stakePosition(memberBob.publicKey, round2.publicKey),
           trusterToMember:AnnaToBob,          
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -808,6 +1820,7 @@ describe("fctr-staking", () => {
           trustCheck: CharlieToBobRound2Check.publicKey,          
           registrarVault,
           round: round2.publicKey,          
+          position: await stakePosition(memberBob.publicKey, round2.publicKey),
           trusterToMember:CharlieToBob,          
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -867,7 +1880,39 @@ describe("fctr-staking", () => {
   
   it("Bob Stake's to round 2 with shared tokens", async () => {  
 
+    // Bob stakes all own tokens first
+    await program.rpc.stake(            
+      new anchor.BN(10*FCTR),
+      { ownFirst: {} },
+      {
+        accounts: {     
+           registrar: registrar.publicKey,           
+           registrarVault,
+           poolMint,
+           beneficiary: Bob.publicKey,
+           member: memberBob.publicKey,
+           memberSigner: memberBobSigner,
+           memberFctrVault: memberBobVault,
+           round: round2.publicKey,
+           position: await stakePosition(memberBob.publicKey, round2.publicKey),
+           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+           tokenProgram: TOKEN_PROGRAM_ID,
+           systemProgram: anchor.web3.SystemProgram.programId,            
+        },
+        signers: [Bob],
+      }
+    );        
+    
+    let _memberAccount = await program.account.member.fetch(
+      memberBob.publicKey
+    );
+    assert.isTrue(_memberAccount.bought.eq(new anchor.BN(0)));
+    assert.isTrue(_memberAccount.trusted.eq(new anchor.BN(5*FCTR)));
+    
+    // And then stakes only trusted tokens
     await program.rpc.stake(            
+      new anchor.BN(5*FCTR),
+      { trustedFirst: {} },
       {
         accounts: {     
            registrar: registrar.publicKey,           
@@ -878,6 +1923,7 @@ describe("fctr-staking", () => {
            memberSigner: memberBobSigner,
            memberFctrVault: memberBobVault,
            round: round2.publicKey,
+           position: await stakePosition(memberBob.publicKey, round2.publicKey),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,            
@@ -908,44 +1954,122 @@ describe("fctr-staking", () => {
     await serumCmn.sleep(10 * 1000);
   }); 
   
-  const round3 = anchor.web3.Keypair.generate();    
+  let round3 = null;
   
-  it("Create round 3", async () => {
-    let final_round = false;
-      
-    await program.rpc.startRound(    
-        final_round,
+  it("Create round 3 by crank", async () => {
+    
+    // Tip cranker with 0.001 SOL
+    await program.rpc.setCrankTip(
+      new anchor.BN(SOL/1000),
       {
-        accounts: {     
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    // Find PDA for round 3
+    const [_round3] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("round")),
+        registrar.publicKey.toBuffer(),
+        new anchor.BN(3).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId
+    );
+    round3 = _round3;
+    
+    // Find PDA paying crank tips and fund it
+    const [crankTipVault] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("crank-seed")),
+        registrar.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(crankTipVault, 1*SOL),
+      "processed"
+    );
+    
+    let _vault_sol_before = await provider.connection.getBalance(vault_sol_account_pda);
+    let _tip_vault_before = await provider.connection.getBalance(crankTipVault);
+    
+    // Bob cranks the next round
+    await program.rpc.crankRound(
+      {
+        accounts: {
            registrar: registrar.publicKey,
-           authority: provider.wallet.publicKey,
-           round: round3.publicKey,           
+           currentRound: round2.publicKey,
+           round: round3,
+           cranker: Bob.publicKey,
+           crankTipVault,
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-           systemProgram: anchor.web3.SystemProgram.programId,            
+           rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+           systemProgram: anchor.web3.SystemProgram.programId,
         },
-        signers: [round3],
-        instructions: [
-          await program.account.round.createInstruction(round3),                        
-        ],
+        signers: [Bob],
       }
-    );    
+    );
+    
+    // Tip is paid by tips vault and SOL vault keeps backing tokens
+    let _vault_sol_after = await provider.connection.getBalance(vault_sol_account_pda);
+    let _tip_vault_after = await provider.connection.getBalance(crankTipVault);
+    assert.strictEqual(_vault_sol_before, _vault_sol_after);
+    assert.strictEqual(_tip_vault_before - _tip_vault_after, SOL/1000);
     
     let roundAccount = await program.account.round.fetch(
-      round3.publicKey
+      round3
     );
     
     assert.isTrue(roundAccount.registrar.equals(registrar.publicKey));
     assert.strictEqual(roundAccount.number, 3);
     assert.ok(roundAccount.finalRound==false);          
+    assert.isTrue(roundAccount.duration.eq(roundTimelock));
+    assert.isTrue(roundAccount.trusterApr.eq(trusterApr));
   });
   
   let memberBobBcdevVault = null;
   
-   
-  it("Unstake from round 2", async () => {         
+  it("Rejects restaking position with trusted tokens", async () => {
     
     memberBobBcdevVault = await serumCmn.createTokenAccount(provider, poolMint, memberBobSigner);  
     
+    let failed = false;
+    try {
+      await program.rpc.restake(
+        false,
+        {
+          accounts: {
+            registrar: registrar.publicKey,
+            registrarSigner,
+            registrarVault,
+            mint,
+            poolMint,
+            beneficiary: Bob.publicKey,
+            member: memberBob.publicKey,
+            memberBcdevVault: memberBobBcdevVault,
+            oldRound: round2.publicKey,
+            oldPosition: await stakePosition(memberBob.publicKey, round2.publicKey),
+            round: round3,
+            position: await stakePosition(memberBob.publicKey, round3),
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          },
+          signers: [Bob],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "RestakeWithTrusters");
+    }
+    assert.isTrue(failed);
+  });
+   
+  it("Unstake from round 2", async () => {         
+    
     let memberBcdevCharlieVault_before = await serumCmn.getTokenAccount(
         provider,
         memberCharlieBcdevVault
@@ -965,9 +2089,11 @@ describe("fctr-staking", () => {
            poolMint,
            beneficiary: Bob.publicKey,
            member: memberBob.publicKey,           
+           memberSigner: memberBobSigner,
            memberFctrVault: memberBobVault,
            memberBcdevVault: memberBobBcdevVault,
            round: round2.publicKey,           
+           position: await stakePosition(memberBob.publicKey, round2.publicKey),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,            
@@ -1017,7 +2143,9 @@ describe("fctr-staking", () => {
        memberBobBcdevVault
      );
     
-    assert.isTrue(memberBcdevVault_after.amount.gt(new anchor.BN(0)));
+    // Bob is the only staker of round 2 and gives half of budget to trusters
+    assert.isTrue(memberBcdevVault_after.amount.eq(round2Budget.div(new anchor.BN(2))));
+    assert.isTrue(_memberAccount.shares.eq(new anchor.BN(0)));
     
      let memberAnnaVault_after = await serumCmn.getTokenAccount(
         provider,
@@ -1101,6 +2229,64 @@ describe("fctr-staking", () => {
    
   });  
   
+  it("Rejects closing the current round 3", async () => {
+    let failed = false;
+    try {
+      await program.rpc.closeRound(
+        {
+          accounts: {
+            registrar: registrar.publicKey,
+            round: round3,
+            payer: provider.wallet.publicKey,
+          },
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "RoundIsInUse");
+    }
+    assert.isTrue(failed);
+  });
+  
+  it("Close round 2 and its burned trust checks", async () => {
+    
+    let _charlie_before = await provider.connection.getBalance(Charlie.publicKey);
+    
+    await program.rpc.closeTrustCheck(
+      {
+        accounts: {
+          trustCheck: CharlieToBobRound2Check.publicKey,
+          payer: Charlie.publicKey,
+        },
+      }
+    );
+    
+    await program.rpc.closeTrustCheck(
+      {
+        accounts: {
+          trustCheck: AnnaToBobRound2Check.publicKey,
+          payer: Anna.publicKey,
+        },
+      }
+    );
+    
+    let _charlie_after = await provider.connection.getBalance(Charlie.publicKey);
+    assert.isTrue(_charlie_after > _charlie_before);
+    
+    await program.rpc.closeRound(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          round: round2.publicKey,
+          payer: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    assert.isNull(await provider.connection.getAccountInfo(round2.publicKey));
+    assert.isNull(await provider.connection.getAccountInfo(CharlieToBobRound2Check.publicKey));
+  });
+  
   
   it("Anna transfer FCTR without sharing program", async () => {
       
@@ -1143,6 +2329,8 @@ describe("fctr-staking", () => {
   it("Charlie Stake's to round 3", async () => {  
 
     await program.rpc.stake(            
+      new anchor.BN(15*FCTR),
+      { ownFirst: {} },
       {
         accounts: {     
            registrar: registrar.publicKey,           
@@ -1152,25 +2340,190 @@ describe("fctr-staking", () => {
            member: memberCharlie.publicKey,
            memberSigner: memberCharlieSigner,
            memberFctrVault: memberCharlieVault,           
-           round: round3.publicKey,           
+           round: round3,           
+           position: await stakePosition(memberCharlie.publicKey, round3),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,  
           
         },
-        signers: [Charlie],
+        signers: [Charlie],
+      }
+    );    
+    
+    let memberAccount = await program.account.member.fetch(
+      memberCharlie.publicKey
+    );
+    assert.isTrue(memberAccount.bought.eq(new anchor.BN(0)));
+    assert.isTrue(memberAccount.trusted.eq(new anchor.BN(0)));  
+    assert.isTrue(memberAccount.staked.eq(new anchor.BN(15*FCTR)));
+    assert.isTrue(memberAccount.stakedTrusted.eq(new anchor.BN(0)));
+    assert.isTrue(memberAccount.shared.eq(new anchor.BN(0))); 
+    assert.isTrue(memberAccount.reward.gt(new anchor.BN(0)));
+  });
+  
+  it("Rejects emergency unstake while round goes on", async () => {
+    
+    // Stuck round path can not be turned off
+    let disabled = false;
+    try {
+      await program.rpc.setEmergencyGracePeriod(
+        new anchor.BN(0),
+        {
+          accounts: {
+            registrar: registrar.publicKey,
+            authority: provider.wallet.publicKey,
+          },
+        }
+      );
+    } catch (err) {
+      disabled = true;
+      assert.strictEqual(err.error.errorCode.code, "InvalidGracePeriod");
+    }
+    assert.isTrue(disabled);
+    
+    await program.rpc.setEmergencyGracePeriod(
+      roundTimelock,
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    let failed = false;
+    try {
+      await program.rpc.emergencyUnstake(
+        {
+          accounts: {
+            registrar: registrar.publicKey,
+            registrarSigner,
+            registrarVault,
+            beneficiary: Charlie.publicKey,
+            member: memberCharlie.publicKey,
+            memberSigner: memberCharlieSigner,
+            memberFctrVault: memberCharlieVault,
+            round: round3,
+            position: await stakePosition(memberCharlie.publicKey, round3),
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [Charlie],
+        }
+      );
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "EmergencyUnstakeNotAllowed");
+    }
+    assert.isTrue(failed);
+    
+    let registrarAccount = await program.account.registrar.fetch(
+      registrar.publicKey
+    );
+    assert.isTrue(registrarAccount.emergencyGracePeriod.eq(roundTimelock));
+  });
+  
+  it("Anna early unstakes from round 3 with redistributed penalty", async () => {
+    
+    // Half of earned reward goes to remaining stakers
+    await program.rpc.setEarlyUnstakePenalty(
+      new anchor.BN(5000),
+      { redistribute: {} },
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          authority: provider.wallet.publicKey,
+        },
+      }
+    );
+    
+    await program.rpc.stake(            
+      new anchor.BN(5*FCTR),
+      { ownFirst: {} },
+      {
+        accounts: {     
+           registrar: registrar.publicKey,           
+           registrarVault,
+           poolMint,
+           beneficiary: Anna.publicKey,
+           member: memberAnna.publicKey,
+           memberSigner: memberAnnaSigner,
+           memberFctrVault: memberAnnaVault,           
+           round: round3,           
+           position: await stakePosition(memberAnna.publicKey, round3),
+           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+           tokenProgram: TOKEN_PROGRAM_ID,
+           systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [Anna],
+      }
+    );
+    
+    await serumCmn.sleep(2 * 1000);
+    
+    let memberBcdevVault_before = await serumCmn.getTokenAccount(
+       provider,
+       memberAnnaBcdevVault
+    );
+    
+    await program.rpc.earlyUnstake(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          registrarSigner,
+          registrarVault,
+          poolMint,
+          beneficiary: Anna.publicKey,
+          member: memberAnna.publicKey,
+          memberFctrVault: memberAnnaVault,
+          memberBcdevVault: memberAnnaBcdevVault,
+          round: round3,
+          position: await stakePosition(memberAnna.publicKey, round3),
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [Anna],
       }
-    );    
+    );
+    
+    const memberVault = await serumCmn.getTokenAccount(
+      provider,
+      memberAnnaVault
+    );
+    assert.isTrue(memberVault.amount.eq(new anchor.BN(15*FCTR)));
     
     let memberAccount = await program.account.member.fetch(
-      memberCharlie.publicKey
+      memberAnna.publicKey
     );
-    assert.isTrue(memberAccount.bought.eq(new anchor.BN(0)));
-    assert.isTrue(memberAccount.trusted.eq(new anchor.BN(0)));  
-    assert.isTrue(memberAccount.staked.eq(new anchor.BN(15*FCTR)));
-    assert.isTrue(memberAccount.stakedTrusted.eq(new anchor.BN(0)));
-    assert.isTrue(memberAccount.shared.eq(new anchor.BN(0))); 
-    assert.isTrue(memberAccount.reward.gt(new anchor.BN(0)));
+    assert.isTrue(memberAccount.bought.eq(new anchor.BN(15*FCTR)));
+    assert.isTrue(memberAccount.staked.eq(new anchor.BN(0)));
+    assert.isTrue(memberAccount.reward.eq(new anchor.BN(0)));
+    
+    // Settled position is closed
+    assert.isNull(
+      await provider.connection.getAccountInfo(
+        await stakePosition(memberAnna.publicKey, round3)
+      )
+    );
+    
+    // Round 3 has budget, so Anna takes only paid reward out of it
+    // and penalty with not earned part stays in the budget for Charlie
+    let memberBcdevVault_after = await serumCmn.getTokenAccount(
+       provider,
+       memberAnnaBcdevVault
+    );
+    let reward = memberBcdevVault_after.amount.sub(memberBcdevVault_before.amount);
+    let roundAccount = await program.account.round.fetch(
+      round3
+    );
+    assert.isTrue(roundAccount.rewardBudget.eq(round2Budget));
+    assert.isTrue(reward.gt(new anchor.BN(0)));
+    assert.isTrue(roundAccount.budgetClaimed.eq(reward));
+    assert.isTrue(roundAccount.rewardMinted.eq(reward));
+    assert.isTrue(roundAccount.penaltyPool.eq(new anchor.BN(0)));
+    assert.isTrue(roundAccount.totalStaked.eq(new anchor.BN(15*FCTR)));
+    assert.strictEqual(roundAccount.activeStakers, 1);
   });
   
   let BobToCharlieRound3Check = anchor.web3.Keypair.generate();    
@@ -1200,7 +2553,8 @@ describe("fctr-staking", () => {
           memberToTrustSigner:memberCharlieSigner,          
           trustCheck: BobToCharlieRound3Check.publicKey,          
           registrarVault,
-          round: round3.publicKey,          
+          round: round3,          
+          position: await stakePosition(memberCharlie.publicKey, round3),
           trusterToMember:BobToCharlie,          
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -1243,7 +2597,7 @@ describe("fctr-staking", () => {
     assert.isTrue(_checkAccount.memberWhoTrust.equals(memberBob.publicKey));
     assert.isTrue(_checkAccount.memberToTrust.equals(memberCharlie.publicKey));
     assert.isTrue(_checkAccount.amount.eq(new anchor.BN(2*FCTR))); 
-    assert.isTrue(_checkAccount.round.equals(round3.publicKey));
+    assert.isTrue(_checkAccount.round.equals(round3));
     assert.isFalse(_checkAccount.burn);
    
   });
@@ -1275,7 +2629,8 @@ describe("fctr-staking", () => {
           memberToTrustSigner:memberAnnaSigner,          
           trustCheck: BobToAnnaRound3Check.publicKey,          
           registrarVault,
-          round: round3.publicKey,          
+          round: round3,          
+          position: await stakePosition(memberAnna.publicKey, round3),
           trusterToMember:BobToAnna,          
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -1317,7 +2672,7 @@ describe("fctr-staking", () => {
     assert.isTrue(_checkAccount.memberWhoTrust.equals(memberBob.publicKey));
     assert.isTrue(_checkAccount.memberToTrust.equals(memberAnna.publicKey));
     assert.isTrue(_checkAccount.amount.eq(new anchor.BN(2*FCTR))); 
-    assert.isTrue(_checkAccount.round.equals(round3.publicKey));
+    assert.isTrue(_checkAccount.round.equals(round3));
     assert.isFalse(_checkAccount.burn);        
   });
   
@@ -1349,7 +2704,8 @@ describe("fctr-staking", () => {
           memberToTrustSigner: memberCharlieSigner,          
           trustCheck: AnnaToCharlieRound3Check.publicKey,          
           registrarVault,
-          round: round3.publicKey,          
+          round: round3,          
+          position: await stakePosition(memberCharlie.publicKey, round3),
           trusterToMember:AnnaToCharlie,          
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,
@@ -1395,10 +2751,49 @@ describe("fctr-staking", () => {
     assert.isTrue(_checkAccount.memberWhoTrust.equals(memberAnna.publicKey));
     assert.isTrue(_checkAccount.memberToTrust.equals(memberCharlie.publicKey));
     assert.isTrue(_checkAccount.amount.eq(new anchor.BN(3*FCTR))); 
-    assert.isTrue(_checkAccount.round.equals(round3.publicKey));
+    assert.isTrue(_checkAccount.round.equals(round3));
     assert.isFalse(_checkAccount.burn);
   });
    
+   it("Rejects taking back shared tokens by round without staked trusted tokens", async () => {     
+    
+    let failed = false;
+    try {
+      await program.rpc.exitSharing(            
+        {
+          accounts: {     
+            registrar:registrar.publicKey,
+            registrarSigner,
+            registrarVault,
+            beneficiary: Anna.publicKey,
+            memberWhoTrust: memberAnna.publicKey,
+            memberWhoTrustSigner: memberAnnaSigner,
+            memberWhoTrustFctrVault:memberAnnaVault,
+            memberToTrust: memberCharlie.publicKey,
+            memberToTrustFctrVault:memberCharlieVault,
+            memberToTrustSigner: memberCharlieSigner,          
+            trustCheck: AnnaToCharlieRound3Check.publicKey,          
+            memberWhoTrustRound: round3,
+            memberWhoTrustPosition: await stakePosition(memberAnna.publicKey, round3),
+            round: round3,          
+            stakedRound: round2.publicKey,
+            position: await stakePosition(memberCharlie.publicKey, round2.publicKey),
+            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+            tokenProgram: TOKEN_PROGRAM_ID,          
+          },
+          signers: [Anna],
+          remainingAccounts: [
+              {pubkey:memberBobVault , isWritable: true , isSigner: false},
+          ]
+        }
+      );    
+    } catch (err) {
+      failed = true;
+      assert.strictEqual(err.error.errorCode.code, "TrustedStakedInOtherRound");
+    }
+    assert.isTrue(failed);
+  });
+   
    it("Anna take back shared tokens and give shared to him", async () => {     
     
     await program.rpc.exitSharing(            
@@ -1416,7 +2811,11 @@ describe("fctr-staking", () => {
           memberToTrustFctrVault:memberCharlieVault,
           memberToTrustSigner: memberCharlieSigner,          
           trustCheck: AnnaToCharlieRound3Check.publicKey,          
-          round: round3.publicKey,          
+          memberWhoTrustRound: round3,
+          memberWhoTrustPosition: await stakePosition(memberAnna.publicKey, round3),
+          round: round3,          
+          stakedRound: round3,
+          position: await stakePosition(memberCharlie.publicKey, round3),
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           tokenProgram: TOKEN_PROGRAM_ID,          
         },
@@ -1481,7 +2880,7 @@ describe("fctr-staking", () => {
       {
         accounts: {     
           registrar:registrar.publicKey,                   
-          round: round3.publicKey,
+          round: round3,
           memberToTrust: memberAnna.publicKey,
           beneficiary: Bob.publicKey, 
           memberWhoTrust: memberBob.publicKey,      
@@ -1519,6 +2918,10 @@ describe("fctr-staking", () => {
       
     await program.rpc.startRound(    
         final_round,
+        roundTimelock,
+        baseApr,
+        trusterApr,
+        null,
       {
         accounts: {     
            registrar: registrar.publicKey,
@@ -1557,9 +2960,11 @@ describe("fctr-staking", () => {
            poolMint,
            beneficiary: Charlie.publicKey,
            member: memberCharlie.publicKey,           
+           memberSigner: memberCharlieSigner,
            memberFctrVault: memberCharlieVault,
            memberBcdevVault: memberCharlieBcdevVault,
-           round: round3.publicKey,           
+           round: round3,           
+           position: await stakePosition(memberCharlie.publicKey, round3),
            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
            tokenProgram: TOKEN_PROGRAM_ID,
            systemProgram: anchor.web3.SystemProgram.programId,            
@@ -1611,7 +3016,7 @@ describe("fctr-staking", () => {
       {
         accounts: {     
           registrar:registrar.publicKey,                   
-          round: round3.publicKey,
+          round: round3,
           memberToTrust: memberCharlie.publicKey,
           beneficiary: Bob.publicKey, 
           memberWhoTrust: memberBob.publicKey,      
@@ -1639,6 +3044,33 @@ describe("fctr-staking", () => {
    
   });
   
+  it("Close round 3 with trust check burned by exit", async () => {
+    
+    let roundAccount = await program.account.round.fetch(
+      round3
+    );
+    assert.strictEqual(roundAccount.openChecks, 0);
+    assert.strictEqual(roundAccount.activeStakers, 0);
+    
+    // Early unstaked Anna and Charlie didn't get more than round budget
+    assert.strictEqual(roundAccount.stakers, 2);
+    assert.isTrue(roundAccount.rewardMinted.gt(roundAccount.budgetClaimed));
+    assert.isTrue(roundAccount.rewardMinted.lte(round2Budget));
+    
+    // Round 3 was cranked by Bob
+    await program.rpc.closeRound(
+      {
+        accounts: {
+          registrar: registrar.publicKey,
+          round: round3,
+          payer: Bob.publicKey,
+        },
+      }
+    );
+    
+    assert.isNull(await provider.connection.getAccountInfo(round3));
+  });
+  
   
   it("Waits for the lockup period to pass", async () => {
     await serumCmn.sleep(10 * 1000);
@@ -1664,7 +3096,8 @@ describe("fctr-staking", () => {
           mint,
           poolMint,
           authority: provider.wallet.publicKey,
-          vaultSolAccount: vault_sol_account_pda,      
+          vaultSolAccount: vault_sol_account_pda,
+          treasury: provider.wallet.publicKey,
           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
           systemProgram: anchor.web3.SystemProgram.programId,  
         },
@@ -1676,4 +3109,553 @@ describe("fctr-staking", () => {
     assert.ok(_vault_sol_after==0);   
   });   
   
+  it("Buys and sells FCTR on a linear curve registrar", async () => {
+    
+    const curveRegistrar = anchor.web3.Keypair.generate();
+    const Dave = anchor.web3.Keypair.generate();
+    const memberDave = anchor.web3.Keypair.generate();
+    
+    const [curveSigner, curveNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [curveRegistrar.publicKey.toBuffer()],
+        program.programId
+      );
+    const [curveVaultSol, curveVaultSolBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("sol-seed")),
+        curveRegistrar.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    const curveMint = await serumCmn.createMint(provider, curveSigner, 12);
+    const curvePoolMint = await serumCmn.createMint(provider, curveSigner, 18);
+    
+    // 0.01 SOL for first FCTR growing by 0.001 SOL for each FCTR
+    await program.rpc.initialize(
+      curveVaultSolBump,
+      curveMint,
+      provider.wallet.publicKey,
+      curveNonce,
+      roundTimelock,     
+      fctrBuyCourse,
+      fctrSellCourse,
+      bcdevSellCourse,
+      { linear: { basePrice: new anchor.BN(SOL/100), slope: new anchor.BN(SOL/1000) } },
+      {
+        accounts: {
+          registrar: curveRegistrar.publicKey,  
+          poolMint: curvePoolMint,
+          vaultSolAccount: curveVaultSol,
+          treasury: provider.wallet.publicKey,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [curveRegistrar],
+        instructions: [
+          await program.account.registrar.createInstruction(curveRegistrar),                            
+        ],
+      }
+    );
+    
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(Dave.publicKey, 2*SOL),
+      "processed"
+    );
+    
+    const [memberDaveSigner, memberDaveNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [curveRegistrar.publicKey.toBuffer(), memberDave.publicKey.toBuffer()],
+        program.programId
+      );
+    const memberDaveVault = await serumCmn.createTokenAccount(provider, curveMint, memberDaveSigner);
+    
+    const tx = program.transaction.createMember(memberDaveNonce, false, [], {
+      accounts: {
+        registrar: curveRegistrar.publicKey,
+        member: memberDave.publicKey,
+        beneficiary: Dave.publicKey,
+        memberSigner: memberDaveSigner,        
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,  
+      },      
+    });
+    await provider.sendAll([{ tx, signers: [memberDave, Dave] }]);
+    
+    const buyAccounts = {
+      registrar: curveRegistrar.publicKey,
+      registrarSigner: curveSigner,
+      mint: curveMint,
+      poolMint: curvePoolMint,
+      buyer: Dave.publicKey,
+      member: memberDave.publicKey,
+      vaultSolAccount: curveVaultSol,
+      treasury: provider.wallet.publicKey,
+      vault: memberDaveVault,
+      clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,  
+    };
+    
+    let _vault_sol_start = await provider.connection.getBalance(curveVaultSol);
+    
+    await program.rpc.buyFctr(
+      new anchor.BN(10*FCTR),
+      new anchor.BN(SOL),
+      { accounts: buyAccounts, signers: [Dave] }
+    );
+    let _vault_sol_first = await provider.connection.getBalance(curveVaultSol);
+    
+    await program.rpc.buyFctr(
+      new anchor.BN(10*FCTR),
+      new anchor.BN(SOL),
+      { accounts: buyAccounts, signers: [Dave] }
+    );
+    let _vault_sol_second = await provider.connection.getBalance(curveVaultSol);
+    
+    // 0.15 SOL for first 10 FCTR and 0.25 SOL for next 10 FCTR
+    let firstPrice = _vault_sol_first - _vault_sol_start;
+    let secondPrice = _vault_sol_second - _vault_sol_first;
+    assert.strictEqual(firstPrice, 15*SOL/100);
+    assert.strictEqual(secondPrice, 25*SOL/100);
+    
+    // Vault covers selling back the whole supply
+    await program.rpc.sellAllFctr(    
+        new anchor.BN(0),
+      {
+        accounts: {     
+          registrar: curveRegistrar.publicKey,
+          memberSigner: memberDaveSigner,
+          mint: curveMint,
+          beneficiary: Dave.publicKey,
+          member: memberDave.publicKey,
+          vaultSolAccount: curveVaultSol,
+          treasury: provider.wallet.publicKey,
+          vault: memberDaveVault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [Dave],
+      }
+    );
+    
+    let _vault_sol_end = await provider.connection.getBalance(curveVaultSol);
+    assert.strictEqual(_vault_sol_end, _vault_sol_start);
+    
+    const memberVault = await serumCmn.getTokenAccount(
+      provider,
+      memberDaveVault
+    );
+    assert.isTrue(memberVault.amount.eq(new anchor.BN(0)));
+  });
+  
+  // Create flat priced registrar with own mints and stake vault
+  const createFlatRegistrar = async () => {
+    const reg = anchor.web3.Keypair.generate();
+    const [signer, regNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [reg.publicKey.toBuffer()],
+        program.programId
+      );
+    const [vaultSol, vaultSolBump] = await anchor.web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("sol-seed")),
+        reg.publicKey.toBuffer(),
+      ],
+      program.programId
+    );
+    const regMint = await serumCmn.createMint(provider, signer, 12);
+    const regPoolMint = await serumCmn.createMint(provider, signer, 18);
+    const regVault = await serumCmn.createTokenAccount(provider, regMint, signer);
+    
+    await program.rpc.initialize(
+      vaultSolBump,
+      regMint,
+      provider.wallet.publicKey,
+      regNonce,
+      roundTimelock,     
+      fctrBuyCourse,
+      fctrSellCourse,
+      bcdevSellCourse,
+      { flat: {} },
+      {
+        accounts: {
+          registrar: reg.publicKey,  
+          poolMint: regPoolMint,
+          vaultSolAccount: vaultSol,
+          treasury: provider.wallet.publicKey,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [reg],
+        instructions: [
+          await program.account.registrar.createInstruction(reg),                            
+        ],
+      }
+    );
+    
+    return {
+      registrar: reg.publicKey,
+      signer,
+      mint: regMint,
+      poolMint: regPoolMint,
+      vault: regVault,
+      vaultSol,
+    };
+  };
+  
+  // Start round of registrar by authority
+  const startRound = async (reg, duration) => {
+    const round = anchor.web3.Keypair.generate();
+    await program.rpc.startRound(    
+        false,
+        new anchor.BN(duration),
+        baseApr,
+        trusterApr,
+        null,
+      {
+        accounts: {     
+           registrar: reg.registrar,
+           authority: provider.wallet.publicKey,
+           round: round.publicKey,           
+           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+           systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [round],
+        instructions: [
+          await program.account.round.createInstruction(round),                        
+        ],
+      }
+    );
+    return round.publicKey;
+  };
+  
+  // Create member of registrar which buys FCTR
+  const createBuyingMember = async (reg, buyingAmount) => {
+    const user = anchor.web3.Keypair.generate();
+    const member = anchor.web3.Keypair.generate();
+    
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(user.publicKey, solAmount),
+      "processed"
+    );
+    
+    const [signer, memberNonce] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [reg.registrar.toBuffer(), member.publicKey.toBuffer()],
+        program.programId
+      );
+    const vault = await serumCmn.createTokenAccount(provider, reg.mint, signer);
+    const bcdevVault = await serumCmn.createTokenAccount(provider, reg.poolMint, signer);
+    
+    const tx = program.transaction.createMember(memberNonce, false, [], {
+      accounts: {
+        registrar: reg.registrar,
+        member: member.publicKey,
+        beneficiary: user.publicKey,
+        memberSigner: signer,        
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,  
+      },      
+    });
+    await provider.sendAll([{ tx, signers: [member, user] }]);
+    
+    await program.rpc.buyFctr(
+      buyingAmount,
+      new anchor.BN(SOL),
+      {
+        accounts: {
+          registrar: reg.registrar,
+          registrarSigner: reg.signer,
+          mint: reg.mint,
+          poolMint: reg.poolMint,
+          buyer: user.publicKey,
+          member: member.publicKey,
+          vaultSolAccount: reg.vaultSol,
+          treasury: provider.wallet.publicKey,
+          vault,
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [user],
+      }
+    );
+    
+    return { user, member: member.publicKey, signer, vault, bcdevVault };
+  };
+  
+  // Stake free tokens of member to round
+  const stakeTo = async (reg, round, who, amount, allocation) => {
+    await program.rpc.stake(            
+      amount,
+      allocation,
+      {
+        accounts: {     
+           registrar: reg.registrar,           
+           registrarVault: reg.vault,
+           poolMint: reg.poolMint,
+           beneficiary: who.user.publicKey,
+           member: who.member,
+           memberSigner: who.signer,
+           memberFctrVault: who.vault,           
+           round,           
+           position: await stakePosition(who.member, round),
+           clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+           tokenProgram: TOKEN_PROGRAM_ID,
+           systemProgram: anchor.web3.SystemProgram.programId,            
+        },
+        signers: [who.user],
+      }
+    );
+  };
+  
+  // Trust tokens to member in round and return trust check
+  const trustTo = async (reg, round, who, to, amount) => {
+    const check = anchor.web3.Keypair.generate();
+    const [trusterToMember] =
+      await anchor.web3.PublicKey.findProgramAddress(
+        [who.user.publicKey.toBuffer(), to.member.toBuffer()],
+        program.programId
+      );
+    await program.rpc.trustToMember(    
+        amount,
+      {
+        accounts: {     
+          registrar: reg.registrar,
+          beneficiary: who.user.publicKey,
+          memberWhoTrust: who.member,
+          memberWhoTrustSigner: who.signer,
+          memberWhoTrustFctrVault: who.vault,
+          memberWhoTrustBcdevVault: who.bcdevVault,          
+          memberToTrust: to.member,
+          memberToTrustFctrVault: to.vault,
+          memberToTrustSigner: to.signer,          
+          trustCheck: check.publicKey,          
+          registrarVault: reg.vault,
+          round,          
+          position: await stakePosition(to.member, round),
+          trusterToMember,          
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,  
+        },
+        signers: [who.user, check],
+        instructions: [
+          await program.account.trustCheck.createInstruction(check),                        
+        ],
+      }
+    );
+    return check.publicKey;
+  };
+  
+  // Take back tokens trusted in round and return tokens of own trusters
+  const exitFrom = async (reg, round, who, to, check, trusters) => {
+    await program.rpc.exitSharing(            
+      {
+        accounts: {     
+          registrar: reg.registrar,
+          registrarSigner: reg.signer,
+          registrarVault: reg.vault,
+          beneficiary: who.user.publicKey,
+          memberWhoTrust: who.member,
+          memberWhoTrustSigner: who.signer,
+          memberWhoTrustFctrVault: who.vault,
+          memberWhoTrustRound: round,
+          memberWhoTrustPosition: await stakePosition(who.member, round),
+          memberToTrust: to.member,
+          memberToTrustFctrVault: to.vault,
+          memberToTrustSigner: to.signer,          
+          trustCheck: check,          
+          round,          
+          stakedRound: round,
+          position: await stakePosition(to.member, round),
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,          
+        },
+        signers: [who.user],
+        remainingAccounts: trusters.map((truster) => (
+          {pubkey: truster.vault, isWritable: true, isSigner: false}
+        )),
+      }
+    );
+  };
+  
+  it("Takes back partially staked trusted tokens of two trusters", async () => {
+    
+    const reg = await createFlatRegistrar();
+    const round = await startRound(reg, 60);
+    
+    const Eve = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    const Frank = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    const Gina = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    const Ivy = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    const Hank = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    
+    // Eve has three trusters and stakes half of trusted tokens
+    const FrankToEve = await trustTo(reg, round, Frank, Eve, new anchor.BN(4*FCTR));
+    await trustTo(reg, round, Gina, Eve, new anchor.BN(2*FCTR));
+    await trustTo(reg, round, Ivy, Eve, new anchor.BN(2*FCTR));
+    await stakeTo(reg, round, Eve, new anchor.BN(4*FCTR), { trustedFirst: {} });
+    
+    // Frank's 4 FCTR are 2 staked and 2 free
+    await exitFrom(reg, round, Frank, Eve, FrankToEve, []);
+    
+    const frankVault = await serumCmn.getTokenAccount(provider, Frank.vault);
+    assert.isTrue(frankVault.amount.eq(new anchor.BN(10*FCTR)));
+    
+    let eveAccount = await program.account.member.fetch(Eve.member);
+    assert.isTrue(eveAccount.trusted.eq(new anchor.BN(2*FCTR)));
+    assert.isTrue(eveAccount.stakedTrusted.eq(new anchor.BN(2*FCTR)));
+    assert.strictEqual(eveAccount.trustedPool.length, 2);
+    
+    let positionAccount = await program.account.stakePosition.fetch(
+      await stakePosition(Eve.member, round)
+    );
+    assert.isTrue(positionAccount.trustedAmount.eq(new anchor.BN(2*FCTR)));
+    
+    // Eve shares to Hank and takes back, Gina and Ivy get 1 staked and 1 free FCTR each
+    const EveToHank = await trustTo(reg, round, Eve, Hank, new anchor.BN(2*FCTR));
+    await exitFrom(reg, round, Eve, Hank, EveToHank, [Gina, Ivy]);
+    
+    for (const truster of [Gina, Ivy]) {
+      const trusterVault = await serumCmn.getTokenAccount(provider, truster.vault);
+      assert.isTrue(trusterVault.amount.eq(new anchor.BN(10*FCTR)));
+    }
+    const eveVault = await serumCmn.getTokenAccount(provider, Eve.vault);
+    assert.isTrue(eveVault.amount.eq(new anchor.BN(10*FCTR)));
+    const hankVault = await serumCmn.getTokenAccount(provider, Hank.vault);
+    assert.isTrue(hankVault.amount.eq(new anchor.BN(10*FCTR)));
+    const stakeVault = await serumCmn.getTokenAccount(provider, reg.vault);
+    assert.isTrue(stakeVault.amount.eq(new anchor.BN(0)));
+    
+    eveAccount = await program.account.member.fetch(Eve.member);
+    assert.isTrue(eveAccount.trusted.eq(new anchor.BN(0)));
+    assert.isTrue(eveAccount.stakedTrusted.eq(new anchor.BN(0)));
+    assert.isTrue(eveAccount.shared.eq(new anchor.BN(0)));
+    assert.strictEqual(eveAccount.trustedPool.length, 0);
+    
+    positionAccount = await program.account.stakePosition.fetch(
+      await stakePosition(Eve.member, round)
+    );
+    assert.isTrue(positionAccount.trustedAmount.eq(new anchor.BN(0)));
+    
+    const roundAccount = await program.account.round.fetch(round);
+    assert.isTrue(roundAccount.totalTrustedStaked.eq(new anchor.BN(0)));
+  });
+  
+  // Move own position from ended round to the current one
+  const restakeTo = async (reg, oldRound, round, who, compoundReward) => {
+    await program.rpc.restake(
+      compoundReward,
+      {
+        accounts: {
+          registrar: reg.registrar,
+          registrarSigner: reg.signer,
+          registrarVault: reg.vault,
+          mint: reg.mint,
+          poolMint: reg.poolMint,
+          beneficiary: who.user.publicKey,
+          member: who.member,
+          memberBcdevVault: who.bcdevVault,
+          oldRound,
+          oldPosition: await stakePosition(who.member, oldRound),
+          round,
+          position: await stakePosition(who.member, round),
+          clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        },
+        signers: [who.user],
+      }
+    );
+  };
+  
+  it("Restakes position with BCDEV reward and with compounded reward", async () => {
+    
+    const reg = await createFlatRegistrar();
+    const roundA = await startRound(reg, 3);
+    
+    const Kate = await createBuyingMember(reg, new anchor.BN(10*FCTR));
+    await stakeTo(reg, roundA, Kate, new anchor.BN(6*FCTR), { ownFirst: {} });
+    
+    await serumCmn.sleep(4 * 1000);
+    const roundB = await startRound(reg, 6);
+    
+    // Reward is minted as BCDEV and principal moves to round B
+    let oldPosition = await program.account.stakePosition.fetch(
+      await stakePosition(Kate.member, roundA)
+    );
+    await restakeTo(reg, roundA, roundB, Kate, false);
+    
+    assert.isNull(
+      await provider.connection.getAccountInfo(await stakePosition(Kate.member, roundA))
+    );
+    let position = await program.account.stakePosition.fetch(
+      await stakePosition(Kate.member, roundB)
+    );
+    assert.isTrue(position.amount.eq(new anchor.BN(6*FCTR)));
+    
+    let kateAccount = await program.account.member.fetch(Kate.member);
+    assert.isTrue(kateAccount.bought.eq(new anchor.BN(4*FCTR)));
+    assert.isTrue(kateAccount.staked.eq(new anchor.BN(6*FCTR)));
+    assert.isTrue(kateAccount.stakedRound.equals(roundB));
+    
+    const bcdevVault = await serumCmn.getTokenAccount(provider, Kate.bcdevVault);
+    assert.isTrue(bcdevVault.amount.eq(oldPosition.reward));
+    let stakeVault = await serumCmn.getTokenAccount(provider, reg.vault);
+    assert.isTrue(stakeVault.amount.eq(new anchor.BN(6*FCTR)));
+    
+    let roundAccount = await program.account.round.fetch(roundA);
+    assert.isTrue(roundAccount.rewardMinted.eq(oldPosition.reward));
+    assert.strictEqual(roundAccount.activeStakers, 0);
+    
+    await serumCmn.sleep(6 * 1000);
+    const roundC = await startRound(reg, 60);
+    
+    // Reward is bought as FCTR at the courses and staked with principal to round C
+    oldPosition = await program.account.stakePosition.fetch(
+      await stakePosition(Kate.member, roundB)
+    );
+    const compounded = oldPosition.reward
+      .mul(fctrBuyCourse)
+      .mul(new anchor.BN(FCTR))
+      .div(bcdevSellCourse.mul(new anchor.BN(10).pow(new anchor.BN(18))));
+    assert.isTrue(compounded.gt(new anchor.BN(0)));
+    
+    await restakeTo(reg, roundB, roundC, Kate, true);
+    
+    assert.isNull(
+      await provider.connection.getAccountInfo(await stakePosition(Kate.member, roundB))
+    );
+    position = await program.account.stakePosition.fetch(
+      await stakePosition(Kate.member, roundC)
+    );
+    assert.isTrue(position.amount.eq(new anchor.BN(6*FCTR).add(compounded)));
+    
+    kateAccount = await program.account.member.fetch(Kate.member);
+    assert.isTrue(kateAccount.bought.eq(new anchor.BN(4*FCTR)));
+    assert.isTrue(kateAccount.staked.eq(new anchor.BN(6*FCTR).add(compounded)));
+    assert.isTrue(kateAccount.totalBought.eq(new anchor.BN(10*FCTR).add(compounded)));
+    
+    const registrarState = await program.account.registrar.fetch(reg.registrar);
+    assert.isTrue(registrarState.solSupply.eq(new anchor.BN(10*FCTR).add(compounded)));
+    
+    stakeVault = await serumCmn.getTokenAccount(provider, reg.vault);
+    assert.isTrue(stakeVault.amount.eq(new anchor.BN(6*FCTR).add(compounded)));
+    
+    // BCDEV vault keeps only the first reward
+    const bcdevVaultAfter = await serumCmn.getTokenAccount(provider, Kate.bcdevVault);
+    assert.isTrue(bcdevVaultAfter.amount.eq(bcdevVault.amount));
+    
+    roundAccount = await program.account.round.fetch(roundB);
+    assert.isTrue(roundAccount.rewardMinted.eq(oldPosition.reward));
+  });
+  
 });